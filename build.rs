@@ -24,6 +24,17 @@ fn store_index(character: char, field: &str, map: &mut BTreeMap<u8, char>) {
 }
 
 
+fn check_mapping(table_name: &str, map: &BTreeMap<u8, char>, index: u8, expected: char) {
+    match map.get(&index) {
+        Some(c) if *c == expected => {},
+        other => panic!(
+            "{} maps 0o{:o} to {:?} instead of {:?}; is encoding.txt malformed?",
+            table_name, index, other, expected,
+        ),
+    }
+}
+
+
 fn main() {
     println!("cargo:rerun-if-changed=src/pdf_reading/encoding.txt");
 
@@ -50,13 +61,12 @@ fn main() {
         let character = if let Some(unprefixed) = pieces[0].strip_prefix("U+") {
             let char_value = u32::from_str_radix(unprefixed, 16)
                 .expect("failed to parse Unicode codepoint as hex");
-            let c = char::from_u32(char_value)
-                .expect("hex value does not map to valid character");
-            c
+            char::from_u32(char_value)
+                .expect("hex value does not map to valid character")
         } else if pieces[0].chars().count() != 1 {
             panic!("character {:?} is actually multiple characters", pieces[0]);
         } else {
-            pieces[0].chars().nth(0).unwrap()
+            pieces[0].chars().next().unwrap()
         };
         char_to_name.insert(character, pieces[1].to_owned());
         store_index(character, pieces[2], &mut index_to_std_char);
@@ -66,6 +76,21 @@ fn main() {
         store_index(character, pieces[6], &mut index_to_sym_char);
     }
 
+    // sanity-check a few well-known mappings to catch format drift in encoding.txt
+    check_mapping("STANDARD_ENCODING", &index_to_std_char, 0o101, 'A');
+    check_mapping("STANDARD_ENCODING", &index_to_std_char, 0o047, '\u{2019}');
+    check_mapping("MAC_ROMAN_ENCODING", &index_to_mac_char, 0o101, 'A');
+    check_mapping("MAC_ROMAN_ENCODING", &index_to_mac_char, 0o216, '\u{E9}');
+    check_mapping("WIN_ANSI_ENCODING", &index_to_win_char, 0o101, 'A');
+    check_mapping("WIN_ANSI_ENCODING", &index_to_win_char, 0o351, '\u{E9}');
+    check_mapping("WIN_ANSI_ENCODING", &index_to_win_char, 0o200, '\u{20AC}');
+    check_mapping("PDF_DOC_ENCODING", &index_to_pdf_char, 0o374, '\u{FC}');
+    check_mapping("SYMBOL_ENCODING", &index_to_sym_char, 0o141, '\u{3B1}');
+    assert_eq!(
+        char_to_name.get(&' ').map(|n| n.as_str()), Some("space"),
+        "space character not named \"space\"; is encoding.txt malformed?",
+    );
+
     let encodings = [
         ("STANDARD_ENCODING", &index_to_std_char),
         ("MAC_ROMAN_ENCODING", &index_to_mac_char),
@@ -78,6 +103,8 @@ fn main() {
     writeln_expect!(output, "// This file has been automatically generated from encoding.txt.");
     writeln_expect!(output, "// Any changes made manually will be lost.");
     writeln_expect!(output);
+    writeln_expect!(output, "#![allow(dead_code)]");
+    writeln_expect!(output);
     writeln_expect!(output);
     writeln_expect!(output, "use std::collections::HashMap;");
     writeln_expect!(output);
@@ -0,0 +1,64 @@
+#!/usr/bin/env python3
+#
+# Generates src/pdf_reading/self_test.pdf, the reference document used by --self-test.
+#
+# Each text object uses a font with a different base encoding and draws bytes whose meaning
+# differs between the encodings; the expected decoding is SELF_TEST_EXPECTED in src/main.rs.
+import os.path
+
+
+FONTS = [
+    # (resource name, base font, encoding object)
+    (b"FStd", b"Helvetica", b"/StandardEncoding"),
+    (b"FMac", b"Helvetica", b"/MacRomanEncoding"),
+    (b"FWin", b"Helvetica", b"/WinAnsiEncoding"),
+    (b"FSym", b"Symbol", b"/SymbolEncoding"),
+    (b"FDif", b"Helvetica", b"<< /Type /Encoding /BaseEncoding /WinAnsiEncoding /Differences [65 /eacute] >>"),
+]
+
+LINES = [
+    # (resource name, string contents as they appear in the content stream)
+    (b"FStd", b"(Standard \\341\\247)"),
+    (b"FMac", b"(MacRoman \\212\\244)"),
+    (b"FWin", b"(WinAnsi \\344\\247\\200)"),
+    (b"FSym", b"(\\141\\142\\147)"),
+    (b"FDif", b"(Differences A)"),
+]
+
+
+def main():
+    content = b""
+    for (i, (font, text)) in enumerate(LINES):
+        content += b"BT /%s 12 Tf 72 %d Td [%s] TJ ET\n" % (font, 720 - 20*i, text)
+
+    font_dict = b" ".join(
+        b"/%s %d 0 R" % (name, 5 + i)
+        for (i, (name, _base, _enc)) in enumerate(FONTS)
+    )
+    objects = [
+        b"<< /Type /Catalog /Pages 2 0 R >>",
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R /Resources << /Font << " + font_dict + b" >> >> >>",
+        b"<< /Length %d >>\nstream\n" % len(content) + content + b"endstream",
+    ]
+    for (_name, base, enc) in FONTS:
+        objects.append(b"<< /Type /Font /Subtype /Type1 /BaseFont /%s /Encoding %s >>" % (base, enc))
+
+    pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n"
+    offsets = []
+    for (i, obj) in enumerate(objects):
+        offsets.append(len(pdf))
+        pdf += b"%d 0 obj\n" % (i + 1) + obj + b"\nendobj\n"
+    xref_offset = len(pdf)
+    pdf += b"xref\n0 %d\n0000000000 65535 f \n" % (len(objects) + 1)
+    for offset in offsets:
+        pdf += b"%010d 00000 n \n" % offset
+    pdf += b"trailer\n<< /Size %d /Root 1 0 R >>\nstartxref\n%d\n%%%%EOF\n" % (len(objects) + 1, xref_offset)
+
+    out_path = os.path.join(os.path.dirname(__file__), "..", "src", "pdf_reading", "self_test.pdf")
+    with open(out_path, "wb") as f:
+        f.write(pdf)
+
+
+if __name__ == "__main__":
+    main()
@@ -1,286 +1,3025 @@
 mod pdf_reading;
 mod regex_ext;
+mod tz_db;
+mod utc_offset;
 
 
-use std::collections::{BTreeMap, HashMap};
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::io::Write as _;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
 
-use clap::Parser;
+use chrono::{NaiveDate, SecondsFormat, Utc};
+use clap::{ArgAction, Parser, ValueEnum};
 use once_cell::sync::Lazy;
-use pdf::content::{Op, TextDrawAdjusted};
-use pdf::file::FileOptions as PdfFileOptions;
-use pdf::font::Font;
-use pdf::object::MaybeRef;
+use pdf::PdfError;
+use pdf::any::AnySync;
+use pdf::content::{Op, TextDrawAdjusted, TextMode};
+use pdf::file::{Cache as PdfCache, File as PdfFile, FileOptions as PdfFileOptions};
+use pdf::object::ParseOptions;
+use pdf::primitive::Primitive;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use crate::pdf_reading::{
-    Coords, bookmark_destination_to_page_index, font_decode, get_destination_pages, get_page_references,
-    get_top_level_bookmarks, Matrix2D, NoNonsenseF32,
+    BookmarkDestination, Coords, bookmark_destination_to_page_index, DecodePath, describe_font_encoding, font_decode,
+    get_all_bookmarks, get_destination_pages, get_info_strings, get_page_fonts, get_page_labels, get_page_references,
+    get_struct_tree_lines, get_top_level_bookmarks, is_vertical_font, Matrix2D, NoNonsenseF32, page_label,
 };
 use crate::regex_ext::SerializableRegex;
+use crate::tz_db::{candidate_zones, country_zones, posix_tz_string};
+use crate::utc_offset::UtcOffset;
 
 
-#[cfg(feature = "parsing_hacks")]
-static ICAO_AND_UTC: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+// the patterns are split at the gap between the ICAO code and "UTC", which may be lazy or greedy
+const ICAO_PATTERN: &str = concat!(
     "\\(",
         "(?P<icao>",
-            "[A-Z0-9]{4}",
+            // text may be decoded in lower case; the code is uppercased when parsed
+            "(?i:[A-Z0-9]{4})",
         ")",
     "\\)",
-    ".+",
-    "UTC",
+);
+
+const UTC_HACKS_PATTERN: &str = concat!(
+    "(?:",
+        // some documents draw each letter as its own fragment, which may end up spaced out
+        "U[ ]?T[ ]?C",
+        "|",
+        "Z", // Zulu
+    ")",
     "[ ]?",
     "(?P<utc>",
-        "[-+\u{2013}]",
+        "[-+\u{2013}\u{2212}]",
         "[0-9]+",
+        "(?::[0-9]{2}|[.,][0-9]{1,2})?",
     ")",
     "(?:",
         "[ ]?",
         "\\(",
             "(?:",
+                "(?:UTC|Z)?", // e.g. "Z-5(Z-4DT)"
                 "(?P<utcdst>", // standard
-                    "[-+\u{2013} ]?",
+                    "[-+\u{2013}\u{2212} ]?",
                     "[0-9]+",
+                    "(?::[0-9]{2}|[.,][0-9]{1,2})?",
                 ")",
                 "|",
                 "(?P<dstutc>", // aberration
                     "[0-9]+",
-                    "[-+\u{2013}]",
+                    "[-+\u{2013}\u{2212}]",
+                ")",
+                "|",
+                "DST[ ]?",
+                "(?P<dstshift>", // shift relative to standard time
+                    "[-+\u{2013}\u{2212}]",
+                    "[0-9]+",
+                ")",
+                "h",
+                "|",
+                "(?P<dtonly>", // summer editions: the offset before is the daylight offset
+                    "DT",
                 ")",
             ")",
             "(?:DT|D|T)?",
         "\\)",
     ")?",
-)).unwrap());
+);
 
-#[cfg(not(feature = "parsing_hacks"))]
-static ICAO_AND_UTC: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+/// A line which ends before the UTC offset, which has wrapped onto the next line.
+static ICAO_AND_UTC_WRAPPED: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
     "\\(",
-        "(?P<icao>",
-            "[A-Z0-9]{4}",
-        ")",
+        "(?i:[A-Z0-9]{4})",
     "\\)",
-    ".+",
-    "UTC",
+    ".+?",
+    "U[ ]?T[ ]?C",
+    "[ ]?",
+    "[-+\u{2013}\u{2212}]?",
+    "[ ]*",
+    "$",
+)).unwrap());
+
+const UTC_STRICT_PATTERN: &str = concat!(
+    // some documents draw each letter as its own fragment, which may end up spaced out
+    "U[ ]?T[ ]?C",
     "(?P<utc>",
-        "[-+\u{2013}]",
+        "[-+\u{2013}\u{2212}]",
         "[0-9]+",
+        "(?::[0-9]{2}|[.,][0-9]{1,2})?",
     ")",
     "(?:",
         "\\(",
             "(?:",
                 "(?P<utcdst>",
-                    "[-+\u{2013}]",
+                    "[-+\u{2013}\u{2212}]",
                     "[0-9]+",
+                    "(?::[0-9]{2}|[.,][0-9]{1,2})?",
                 ")",
             ")",
             "DT",
         "\\)",
     ")?",
+);
+
+// all variants are always compiled so that they can be selected at runtime
+static ICAO: Lazy<Regex> = Lazy::new(|| Regex::new(ICAO_PATTERN).unwrap());
+static UTC_HACKS: Lazy<Regex> = Lazy::new(|| Regex::new(UTC_HACKS_PATTERN).unwrap());
+static UTC_STRICT: Lazy<Regex> = Lazy::new(|| Regex::new(UTC_STRICT_PATTERN).unwrap());
+static ICAO_AND_UTC_HACKS_LAZY: Lazy<Regex> = Lazy::new(|| icao_and_utc_regex(GapMatching::Lazy, UTC_HACKS_PATTERN));
+static ICAO_AND_UTC_HACKS_GREEDY: Lazy<Regex> = Lazy::new(|| icao_and_utc_regex(GapMatching::Greedy, UTC_HACKS_PATTERN));
+static ICAO_AND_UTC_STRICT_LAZY: Lazy<Regex> = Lazy::new(|| icao_and_utc_regex(GapMatching::Lazy, UTC_STRICT_PATTERN));
+static ICAO_AND_UTC_STRICT_GREEDY: Lazy<Regex> = Lazy::new(|| icao_and_utc_regex(GapMatching::Greedy, UTC_STRICT_PATTERN));
+
+static COORDINATES: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+    "(?P<latdir>",
+        "[NS]",
+    ")",
+    "[ ]?",
+    "(?P<latdeg>",
+        "[0-9]{1,2}",
+    ")",
+    "\u{B0}",
+    "(?P<latmin>",
+        "[0-9]{1,2}",
+        "(?:[.,][0-9]+)?",
+    ")",
+    "[\u{B4}\u{2032}']?",
+    "[ ]*",
+    "(?P<londir>",
+        "[EW]",
+    ")",
+    "[ ]?",
+    "(?P<londeg>",
+        "[0-9]{1,3}",
+    ")",
+    "\u{B0}",
+    "(?P<lonmin>",
+        "[0-9]{1,2}",
+        "(?:[.,][0-9]+)?",
+    ")",
+)).unwrap());
+
+/// Reference document for --self-test; generated by contrib/make_self_test_pdf.py.
+const SELF_TEST_PDF: &[u8] = include_bytes!("pdf_reading/self_test.pdf");
+const SELF_TEST_EXPECTED: &str = "Standard \u{C6}\u{A7}\nMacRoman \u{E4}\u{A7}\nWinAnsi \u{E4}\u{A7}\u{20AC}\n\u{3B1}\u{3B2}\u{3B3}\nDifferences \u{E9}\n";
+
+static EFFECTIVE_DATE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+    "(?i)",
+    "effective",
+    ".{0,40}?",
+    "(?:",
+        "(?P<day>",
+            "[0-9]{1,2}",
+        ")",
+        "[ ]*",
+        "(?P<month>",
+            "[A-Z]{3}",
+        ")",
+        "[A-Z]*",
+        "[ ]*",
+        "(?P<year>",
+            "[0-9]{4}",
+        ")",
+        "|",
+        "(?P<iso>",
+            "[0-9]{4}-[0-9]{2}-[0-9]{2}",
+        ")",
+    ")",
 )).unwrap());
 
 
+
 #[derive(Parser)]
 struct Opts {
+    /// Time zone definition files in TOML or (with a .yaml or .yml extension) YAML format. May be
+    /// given multiple times; definitions in later files replace those with the same name in
+    /// earlier files.
     #[arg(short, long, default_value = "time_zones.toml")]
-    pub time_zones: PathBuf,
+    pub time_zones: Vec<PathBuf>,
+
+    /// Do not read any time zone definition files; all airports are output as unmatched.
+    #[arg(long)]
+    pub no_config: bool,
+
+    /// Skip text drawn with a rotated text matrix (e.g. vertical column labels) instead of
+    /// including it in line assembly.
+    #[arg(long)]
+    pub skip_rotated: bool,
+
+    /// Which text to extract: visible text, invisible text (usually an OCR layer on top of a
+    /// scanned page) or both.
+    #[arg(long, value_enum, default_value_t = TextLayer::Both)]
+    pub text_layer: TextLayer,
+
+    /// Whether to take the rotation of pages (/Rotate) into account when determining the reading
+    /// order of text and whether it is rotated.
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    pub honor_rotation: bool,
+
+    /// JSON or JSON Lines output of a previous run. Airports whose time zone changed, were added or
+    /// were removed relative to it are reported on stderr.
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+
+    /// Instead of reading time zone definition files, list all zones from the time zone database
+    /// whose current offsets match those of each airport.
+    #[arg(long)]
+    pub timezone_db: bool,
+
+    /// With --timezone-db, only list zones in the regions (e.g. "America/") plausible for the
+    /// first letter of the airport's ICAO code.
+    #[arg(long)]
+    pub use_region_hints: bool,
+
+    /// With --timezone-db, only list zones of the airport's country, taken from this CSV file of
+    /// ICAO codes and ISO 3166 alpha-2 country codes (one "ICAO,CC" pair per line; lines starting
+    /// with "#", such as a header, are skipped). Airports missing from the file are matched as
+    /// usual.
+    #[arg(long, value_name = "CSV")]
+    pub icao_countries: Option<PathBuf>,
+
+    /// Do not report bookmarks with unsupported actions or strings ending in a truncated two-byte
+    /// character code.
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Skip text drawn at a font size (in user space units) smaller than this, e.g. fine print.
+    #[arg(long, default_value_t = 0.0)]
+    pub min_font_size: f32,
+
+    /// Extract from every page of each document instead of only the airport directory found via
+    /// the bookmarks.
+    #[arg(long)]
+    pub per_page: bool,
+
+    /// Process this many documents at the same time. Each document being processed is held in
+    /// memory in its entirety along with its object cache, so memory use grows with this value.
+    /// Results are still output in the order in which the documents were given, but only once
+    /// every document processed alongside them has been completed.
+    #[arg(long, default_value = "1")]
+    pub pdf_parallelism: usize,
+
+    /// Process the pages of each document on this many threads. The threads share the document
+    /// and its object cache, so this costs little additional memory, but diagnostic output of the
+    /// pages may be interleaved.
+    #[arg(long, default_value = "1")]
+    pub threads_per_pdf: usize,
+
+    /// Abandon a document (with a warning) if processing it takes longer than this many seconds,
+    /// and carry on with the next one. Results are then only output once a document has been
+    /// processed completely. An abandoned document cannot be stopped; it continues to take up
+    /// processor time and memory in the background until the program exits.
+    #[arg(long, value_name = "SECONDS")]
+    pub timeout: Option<f64>,
+
+    /// Do not work around known errors in documents even if the program has been built with the
+    /// `parsing_hacks` feature. Without that feature, parsing is always strict.
+    #[arg(long)]
+    pub strict_parsing: bool,
+
+    /// How to match the text between the ICAO code and "UTC" if a line mentions UTC more than
+    /// once: take the first (lazy), the last (greedy) or the one drawn nearest to the ICAO code
+    /// (nearest) offset.
+    #[arg(long, value_enum, default_value_t = GapMatching::Lazy)]
+    pub gap: GapMatching,
+
+    /// Skip airports whose standard or daylight saving offset is less than this, as such offsets
+    /// indicate a parsing error.
+    #[arg(long, default_value = "-12", allow_hyphen_values = true)]
+    pub min_offset: UtcOffset,
+
+    /// Skip airports whose standard or daylight saving offset is greater than this, as such offsets
+    /// indicate a parsing error.
+    #[arg(long, default_value = "+14", allow_hyphen_values = true)]
+    pub max_offset: UtcOffset,
+
+    /// Read a comma in offsets and coordinates as a decimal separator, as in "UTC+5,5" or
+    /// "N40°38,39´", instead of a point.
+    #[arg(long)]
+    pub decimal_comma: bool,
+
+    /// Assemble text fragments whose vertical positions differ by at most this fraction of the
+    /// page height into the same line.
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.0)]
+    pub line_tolerance: f32,
+
+    /// How fragments of a line at the same horizontal position (e.g. a base character and a
+    /// combining mark drawn separately) are ordered.
+    #[arg(long, value_enum, default_value_t = XTieBreak::Vertical)]
+    pub x_tie_break: XTieBreak,
+
+    /// Split lines into table cells wherever consecutive text fragments start more than this
+    /// fraction of the page width apart, and look for airports in each cell. If no cell contains
+    /// an airport, the whole line is searched as usual.
+    #[arg(long, value_name = "FRACTION")]
+    pub cell_gap: Option<f32>,
+
+    /// Drop text placed outside the page's MediaBox expanded by this many points on each side, as
+    /// such placements are usually the result of a nonsensical text matrix.
+    #[arg(long, value_name = "POINTS")]
+    pub bounds_margin: Option<f32>,
+
+    /// Start extracting at the page of this named destination (or, if the document has no such
+    /// destination, at this 1-based page number) instead of looking for the airport directory
+    /// bookmark.
+    #[arg(long)]
+    pub start_dest: Option<String>,
+
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Output the ICAO codes of the airports grouped by time zone instead of one airport per line
+    /// (text) or object (JSON). Has no effect on GeoJSON and JSON Lines output.
+    #[arg(long)]
+    pub group_by_zone: bool,
+
+    /// Label each airport with the most specific bookmark (at any nesting level) covering its
+    /// page, e.g. the region within the airport directory. Adds a section field to JSON output
+    /// and groups text output under section headings.
+    #[arg(long)]
+    pub split_by_section: bool,
+
+    /// Describe the encoding of each font on each processed page (on standard error) to help debug
+    /// text that fails to decode.
+    #[arg(long)]
+    pub dump_fonts: bool,
+
+    /// Report (on standard error) how many characters of each font could not be decoded on each
+    /// page, with some examples of their codes, to reveal incomplete encodings.
+    #[arg(long)]
+    pub report_undecoded: bool,
+
+    /// Only decode and print all the text on the page with this (zero-based) index, without
+    /// assembling lines or looking for airports, to check whether its fonts can be decoded at all.
+    #[arg(long, value_name = "PAGE_INDEX")]
+    pub decode_only: Option<u32>,
+
+    /// Only list the airports whose offsets match no time zone definition, along with the line
+    /// each was parsed from, to find the definitions a document is missing.
+    #[arg(long)]
+    pub list_unmatched: bool,
+
+    /// Decode the built-in reference document, which uses a font for each supported base
+    /// encoding, and check the result against the expected text.
+    #[arg(long)]
+    pub self_test: bool,
+
+    /// Write the positioned text of each processed page into this directory as an SVG image
+    /// (named after the document and the zero-based page index), to inspect the layout that lines
+    /// are assembled from.
+    #[arg(long, value_name = "DIR")]
+    pub dump_svg: Option<PathBuf>,
+
+    /// Write a report for each processed page into this directory (named after the document and
+    /// the zero-based page index), listing the matched and unmatched airports along with all the
+    /// lines assembled from the page.
+    #[arg(long, value_name = "DIR")]
+    pub report_dir: Option<PathBuf>,
+
+    /// Print (on standard error) every line assembled from the text of each processed page.
+    #[arg(long)]
+    pub dump_lines: bool,
+
+    /// Insert this between the text fragments of a line when displaying it (e.g. with
+    /// --dump-lines), to show how the line was assembled. Matching always uses the fragments
+    /// without separators.
+    #[arg(long, default_value = "")]
+    pub fragment_separator: String,
+
+    /// Collapse runs of whitespace and control characters in assembled lines into single spaces
+    /// and trim them before matching.
+    #[arg(long)]
+    pub normalize_whitespace: bool,
+
+    /// Pipe the assembled lines through this shell command before matching them, e.g. to fix up
+    /// the idiosyncrasies of a document. By default, the lines of each page are passed on standard
+    /// input at once, one per line, and the command must output the same number of lines. Lines
+    /// the command changes are no longer split into cells (see --cell-gap), and table rows it
+    /// changes are skipped if the time zone file configures column bands.
+    ///
+    /// The command is run by the system shell with the privileges of this program; never pass a
+    /// command taken from an untrusted source.
+    #[arg(long, value_name = "COMMAND")]
+    pub preprocess: Option<String>,
+
+    /// Run the --preprocess command once for each line instead of once for each page. Slower, but
+    /// suits commands which only handle a single line.
+    #[arg(long)]
+    pub preprocess_per_line: bool,
+
+    /// Assemble lines from the structure tree of tagged documents instead of from the positions of
+    /// the text: each table row, and the directly contained text of every other structure
+    /// element, becomes a line. Pages without tagged content are read as usual.
+    #[arg(long)]
+    pub use_struct_tree: bool,
+
+    /// Output a summary of the extraction (pages and lines read, airports parsed, characters not
+    /// decoded, airports matched and unmatched) on stderr at the end.
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Print the first N lines matching an airport along with what was parsed from them, then
+    /// exit. Useful to quickly check whether a new document is understood at all.
+    #[arg(long, value_name = "N")]
+    pub sample: Option<usize>,
+
+    /// Append the standard and daylight UTC offsets read from the document to the text output.
+    #[arg(long)]
+    pub show_offsets: bool,
+
+    /// Instead of "?", output a synthetic time zone for airports whose offsets match no definition.
+    #[arg(long, value_enum)]
+    pub emit_unmatched_as: Option<UnmatchedFallback>,
+
+    /// Warn about airports whose standard offset deviates by more than two hours from the one
+    /// expected from their longitude.
+    #[arg(long)]
+    pub sanity_longitude: bool,
+
+    /// With --format geojson, include airports without coordinates (with a null geometry).
+    #[arg(long)]
+    pub include_unlocated: bool,
+
+    /// Only output airports whose extraction confidence is at least this level.
+    #[arg(long, value_enum, default_value_t = Confidence::Low)]
+    pub min_confidence: Confidence,
+
+    /// Only output airports whose ICAO code starts with this prefix, e.g. "E" for northern Europe
+    /// or "LO" for Austria. May be given multiple times.
+    #[arg(long)]
+    pub country_prefix: Vec<String>,
+
+    /// Check that each time zone definition and extra pattern is unchanged by serializing and
+    /// reparsing it (and each definition also by rebuilding it from its parts), report those that
+    /// are not, and exit.
+    #[arg(long)]
+    pub validate_roundtrip: bool,
+
+    /// Record each completely processed PDF in this file (one path per line) and skip the PDFs
+    /// already recorded in it, so that an interrupted batch run can be resumed.
+    #[arg(long, value_name = "FILE")]
+    pub resume: Option<PathBuf>,
+
+    /// Only output airports whose time zone differs from the one recorded for them in this JSON
+    /// file (or which are not recorded in it yet), then record the time zones of all airports
+    /// found. Keeps track of changes across runs over many editions of the documents.
+    #[arg(long, value_name = "STORE")]
+    pub only_changed: Option<PathBuf>,
+
+    /// The password with which to open encrypted documents. Documents that are only protected by
+    /// an owner password (i.e. with an empty user password) are opened without one.
+    #[arg(long)]
+    pub password: Option<String>,
 
     pub pdf_paths: Vec<PathBuf>,
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+    /// A GeoJSON FeatureCollection of airport locations.
+    Geojson,
+    /// JSON Lines: one JSON object per airport, output as soon as the airport is found.
+    Jsonl,
+    /// Like text, but with the POSIX TZ string (e.g. "CET-1CEST,M3.5.0,M10.5.0/3") of the
+    /// matched zone, or its name if its rules cannot be expressed as one.
+    Posix,
+}
+
+/// Which text to extract, depending on its rendering mode.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ValueEnum)]
+enum TextLayer {
+    /// Only visible text.
+    Visible,
+    /// Only invisible text, which is usually an OCR layer on top of a scanned page.
+    Ocr,
+    /// Both; where both layers contain the same text at the same position, it is only taken once.
+    Both,
+}
+impl TextLayer {
+    pub fn includes(&self, invisible: bool) -> bool {
+        match self {
+            Self::Visible => !invisible,
+            Self::Ocr => invisible,
+            Self::Both => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ValueEnum)]
+enum UnmatchedFallback {
+    /// The fixed-offset Etc/GMT zone corresponding to the standard offset.
+    #[value(name = "Etc/GMT")]
+    EtcGmt,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Hash, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 struct TimeZoneDefinition {
     pub icao_match: Option<SerializableRegex>,
     pub iana: String,
-    pub utc_standard: i8,
-    pub utc_daylight: Option<i8>,
+    pub utc_standard: UtcOffset,
+    pub utc_daylight: Option<UtcOffset>,
+}
+impl TimeZoneDefinition {
+    /// Creates a definition without a daylight saving offset that applies to all airports.
+    ///
+    /// Together with the `with_...` methods, this constructs definitions in code instead of
+    /// deserializing them, e.g. `TimeZoneDefinition::new("Europe/Vienna", UtcOffset::from_minutes(60))`
+    /// followed by `.with_utc_daylight(UtcOffset::from_minutes(120))` and `.with_icao_match("^LO")?`.
+    pub fn new<I: Into<String>>(iana: I, utc_standard: UtcOffset) -> Self {
+        Self {
+            icao_match: None,
+            iana: iana.into(),
+            utc_standard,
+            utc_daylight: None,
+        }
+    }
+
+    /// Sets the daylight saving offset.
+    pub fn with_utc_daylight(mut self, utc_daylight: UtcOffset) -> Self {
+        self.utc_daylight = Some(utc_daylight);
+        self
+    }
+
+    /// Restricts the definition to airports whose ICAO code matches the given regular expression,
+    /// which is compiled the same way as when deserializing.
+    pub fn with_icao_match(mut self, icao_match: &str) -> Result<Self, regex::Error> {
+        self.icao_match = Some(SerializableRegex(Regex::new(icao_match)?));
+        Ok(self)
+    }
+}
+
+/// How the daylight saving offset in the document is compared to that of a time zone definition.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DstMatching {
+    /// The daylight saving offsets must be equal; a missing one only matches a missing one.
+    #[default]
+    Strict,
+
+    /// The daylight saving offsets are not compared.
+    Ignore,
+
+    /// A daylight saving offset missing from the document matches any definition; otherwise, the
+    /// offsets must be equal.
+    Lenient,
+}
+impl DstMatching {
+    pub fn matches(&self, document: Option<UtcOffset>, definition: Option<UtcOffset>) -> bool {
+        match self {
+            Self::Strict => document == definition,
+            Self::Ignore => true,
+            Self::Lenient => document.is_none() || document == definition,
+        }
+    }
+}
+
+/// How the offsets in the document are compared to those of a time zone definition.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum OffsetMatching {
+    /// The offsets must be equal and a multiple of 15 minutes, as those of all current time zones
+    /// are; any other offset in the document is left unmatched.
+    #[default]
+    Standard,
+
+    /// The offsets must be equal to the minute, whatever they are. For historical documents giving
+    /// local mean time, such as UTC+0:53.
+    RawMinutes,
+}
+impl OffsetMatching {
+    pub fn allows(&self, document: UtcOffset) -> bool {
+        match self {
+            Self::Standard => document.minutes() % 15 == 0,
+            Self::RawMinutes => true,
+        }
+    }
+}
+
+/// The contents of a time zone file: the time zone definitions, keyed by name, along with
+/// top-level options governing how they are matched.
+#[derive(Clone, Debug, Default, Deserialize)]
+struct TimeZoneFile {
+    #[serde(default)]
+    pub dst_matching: Option<DstMatching>,
+    #[serde(default)]
+    pub offset_matching: Option<OffsetMatching>,
+    #[serde(default)]
+    pub extra_patterns: HashMap<String, SerializableRegex>,
+    #[serde(default)]
+    pub effective: Option<String>,
+    #[serde(default)]
+    pub column_bands: Option<ColumnBands>,
+    #[serde(flatten)]
+    pub definitions: HashMap<String, TimeZoneDefinition>,
+}
+
+/// The horizontal extents (left and right edge) of the table columns from which the ICAO code and
+/// the UTC offsets are read, as fractions of the page width. A fragment belongs to a column if it
+/// starts within its extent.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+struct ColumnBands {
+    pub icao: (f32, f32),
+    pub utc: (f32, f32),
+}
+impl ColumnBands {
+    /// Extracts the text of the ICAO and UTC columns from a line, or `None` if either is empty.
+    pub fn cells(&self, line: &AssembledLine) -> Option<(String, String)> {
+        let mut icao_cell = String::new();
+        let mut utc_cell = String::new();
+        for (index, (start, x)) in line.fragment_offsets.iter().enumerate() {
+            let end = line.fragment_offsets.get(index + 1)
+                .map(|(next_start, _)| *next_start)
+                .unwrap_or(line.text.len());
+            let x = f32::from(*x);
+            let within = |(left, right): (f32, f32)| x >= left && x <= right;
+            if within(self.icao) {
+                icao_cell.push_str(&line.text[*start..end]);
+            } else if within(self.utc) {
+                utc_cell.push_str(&line.text[*start..end]);
+            }
+        }
+        let (icao_cell, utc_cell) = (icao_cell.trim(), utc_cell.trim());
+        if icao_cell.is_empty() || utc_cell.is_empty() {
+            None
+        } else {
+            Some((icao_cell.to_owned(), utc_cell.to_owned()))
+        }
+    }
+}
+
+/// The time zone definitions and matching options collected from all time zone files.
+#[derive(Clone, Debug, Default)]
+struct TimeZoneConfig {
+    pub dst_matching: DstMatching,
+    pub offset_matching: OffsetMatching,
+    /// Additional patterns applied to each airport's line, whose captures are attached to the
+    /// result.
+    pub extra_patterns: HashMap<String, SerializableRegex>,
+    /// The effective date (YYYY-MM-DD) of the edition of the documents the definitions were
+    /// written for.
+    pub effective: Option<String>,
+    /// The horizontal extents of the table columns holding the ICAO code and the UTC offsets, for
+    /// documents laid out as tables.
+    pub column_bands: Option<ColumnBands>,
+    pub definitions: HashMap<String, TimeZoneDefinition>,
+    /// The ISO 3166 country code of each airport (by ICAO code), from --icao-countries.
+    pub icao_countries: HashMap<String, String>,
+}
+
+/// How trustworthy an extracted result is, depending on how its text was decoded and whether any
+/// workarounds for typographical errors were necessary.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+impl Confidence {
+    pub fn from_decode_path(decode_path: DecodePath) -> Self {
+        match decode_path {
+            DecodePath::Raw => Self::Low,
+            DecodePath::BaseEncoding => Self::Medium,
+            DecodePath::ToUnicode => Self::High,
+        }
+    }
+
+    pub fn downgrade(self) -> Self {
+        match self {
+            Self::High => Self::Medium,
+            Self::Medium|Self::Low => Self::Low,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct AirportResult {
+    pub icao: String,
+    pub iana: Option<String>,
+    pub utc_standard: UtcOffset,
+    pub utc_daylight: Option<UtcOffset>,
+    pub confidence: Confidence,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    /// The zero-based index of the page on which the airport was found.
+    #[serde(default)]
+    pub page: u32,
+    /// The label of the page on which the airport was found, as printed in the document (e.g.
+    /// "A-23"), if the document has page labels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_label: Option<String>,
+    /// The title of the most specific bookmark covering the page on which the airport was found,
+    /// with --split-by-section.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// The position of the start of the line on which the airport was found.
+    #[serde(skip)]
+    pub position: Coords,
+    /// The text of the line on which the airport was found.
+    #[serde(skip)]
+    pub line: String,
+    /// The values captured from the line by the extra patterns in the time zone file.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
+/// An airport whose offsets did not match any time zone definition.
+#[derive(Clone, Debug, PartialEq)]
+struct UnmatchedAirport {
+    pub icao: String,
+    pub utc_standard: UtcOffset,
+    pub utc_daylight: Option<UtcOffset>,
+    /// The text of the line on which the airport was found.
+    pub line: String,
+}
+
+/// The airports found in a document, split by whether their offsets matched a time zone
+/// definition.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ExtractionOutput {
+    pub matched: Vec<AirportResult>,
+    pub unmatched: Vec<UnmatchedAirport>,
+}
+impl ExtractionOutput {
+    pub fn push(&mut self, result: AirportResult) {
+        if result.iana.is_some() {
+            self.matched.push(result);
+        } else {
+            self.unmatched.push(UnmatchedAirport {
+                icao: result.icao,
+                utc_standard: result.utc_standard,
+                utc_daylight: result.utc_daylight,
+                line: result.line,
+            });
+        }
+    }
+}
+
+/// Counts of what happened at each stage of extraction.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+struct ExtractionStats {
+    /// The pages whose contents were read.
+    pub pages_processed: usize,
+    /// The lines assembled from the text fragments (or the structure tree) of those pages.
+    pub lines_assembled: usize,
+    /// The airports parsed from lines, including those then skipped for implausible offsets.
+    pub regex_hits: usize,
+    /// The character codes that could not be decoded, including those of strings whose font
+    /// provides no way of decoding them at all.
+    pub decode_failures: usize,
+    /// The airports whose offsets matched a time zone definition.
+    pub matched: usize,
+    /// The airports whose offsets matched no time zone definition.
+    pub unmatched: usize,
+}
+impl AddAssign for ExtractionStats {
+    fn add_assign(&mut self, other: Self) {
+        self.pages_processed += other.pages_processed;
+        self.lines_assembled += other.lines_assembled;
+        self.regex_hits += other.regex_hits;
+        self.decode_failures += other.decode_failures;
+        self.matched += other.matched;
+        self.unmatched += other.unmatched;
+    }
+}
+impl fmt::Display for ExtractionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} pages, {} lines, {} airports parsed, {} characters not decoded, {} matched, {} unmatched",
+            self.pages_processed, self.lines_assembled, self.regex_hits, self.decode_failures,
+            self.matched, self.unmatched,
+        )
+    }
 }
 
 
-fn normalize_offset(offset: &str) -> i8 {
-    let mut mod_offset = offset.replace('\u{2013}', "-");
+/// Returns the number with a point as its decimal separator. A comma is taken as the decimal
+/// separator if `decimal_comma` is set. Otherwise, a comma not followed by a digit is taken as the
+/// end of the number, and one followed by a digit is kept, so that the number fails to parse
+/// instead of being silently truncated.
+fn with_decimal_point(number: &str, decimal_comma: bool) -> String {
+    if decimal_comma {
+        return number.replace(',', ".");
+    }
+    match number.find(',') {
+        Some(comma_index) if !number[comma_index+1..].starts_with(|c: char| c.is_ascii_digit()) => {
+            number[..comma_index].to_owned()
+        },
+        _ => number.to_owned(),
+    }
+}
+
+/// Normalizes a captured offset such as "+5", "\u{2013}4" or " 5:30". Fails if the capture is
+/// not a decimal offset (e.g. "0x5" from garbled text) or out of range.
+fn normalize_offset(offset: &str, decimal_comma: bool) -> Result<UtcOffset, String> {
+    // en dash and minus sign (the latter is common when the sign is drawn as a separate glyph)
+    let mut mod_offset = with_decimal_point(offset, decimal_comma)
+        .replace(['\u{2013}', '\u{2212}'], "-");
     if mod_offset.starts_with('+') || mod_offset.starts_with(' ') {
         mod_offset.remove(0);
     }
-    mod_offset.parse().unwrap()
+    // only a sign followed by decimal digits and separators
+    let unsigned = mod_offset.strip_prefix('-').unwrap_or(&mod_offset);
+    let valid = unsigned.starts_with(|c: char| c.is_ascii_digit())
+        && unsigned.chars().all(|c| c.is_ascii_digit() || c == ':' || c == '.');
+    if !valid {
+        if mod_offset.contains(',') {
+            return Err(format!("malformed UTC offset {:?} (is the comma a decimal separator? see --decimal-comma)", offset));
+        }
+        return Err(format!("malformed UTC offset {:?}", offset));
+    }
+    mod_offset.parse()
 }
-fn normalize_reverse_offset(offset: &str) -> i8 {
+/// Normalizes an offset with a trailing sign, such as "12-". The sign may be a plus, a hyphen or a
+/// typeset dash ("12\u{2013}", "12\u{2212}") spanning multiple bytes.
+fn normalize_reverse_offset(offset: &str) -> Result<UtcOffset, String> {
     // "12-" -> "-12"
-    let offset_chars: Vec<char> = offset.chars().collect();
-    let mut regular_offset = String::with_capacity(offset.len());
-    for c in &offset_chars[offset_chars.len()-1..] {
-        regular_offset.push(*c);
+    let (sign_index, sign) = offset.char_indices().next_back()
+        .ok_or_else(|| "empty reverse UTC offset".to_owned())?;
+    // only whole hours followed by a single sign; anything else (e.g. "-5", whose last digit would
+    // be taken for the sign) is rejected rather than rearranged
+    let hours = &offset[..sign_index];
+    let valid = matches!(sign, '-' | '+' | '\u{2013}' | '\u{2212}')
+        && !hours.is_empty()
+        && hours.chars().all(|c| c.is_ascii_digit());
+    if !valid {
+        return Err(format!("malformed reverse UTC offset {:?}", offset));
     }
-    for c in &offset_chars[0..offset_chars.len()-1] {
-        regular_offset.push(*c);
+    let regular_offset = format!("{}{}", sign, hours);
+    normalize_offset(&regular_offset, false)
+}
+
+/// Finds an effective date such as "EFFECTIVE 0901Z 16 MAY 2024" or "effective 2024-05-16" in the
+/// text.
+fn parse_effective_date(text: &str) -> Option<NaiveDate> {
+    let caps = EFFECTIVE_DATE.captures(text)?;
+    if let Some(iso) = caps.name("iso") {
+        return NaiveDate::parse_from_str(iso.as_str(), "%Y-%m-%d").ok();
     }
-    normalize_offset(&regular_offset)
+    let date_string = format!("{} {} {}", &caps["day"], &caps["month"], &caps["year"]);
+    NaiveDate::parse_from_str(&date_string, "%d %b %Y").ok()
 }
 
 
-fn main() {
-    let opts = Opts::parse();
+/// Parses the first pair of coordinates such as "N40°38.39´ W73°46.73´" in the line into decimal
+/// degrees of latitude and longitude.
+fn parse_coordinates(line: &str, decimal_comma: bool) -> Option<(f64, f64)> {
+    let caps = COORDINATES.captures(line)?;
+    let degrees = |deg_name: &str, min_name: &str, negative: bool| -> Option<f64> {
+        let deg: f64 = caps.name(deg_name)?.as_str().parse().ok()?;
+        let min: f64 = with_decimal_point(caps.name(min_name)?.as_str(), decimal_comma).parse().ok()?;
+        let value = deg + min / 60.0;
+        Some(if negative { -value } else { value })
+    };
+    let latitude = degrees("latdeg", "latmin", &caps["latdir"] == "S")?;
+    let longitude = degrees("londeg", "lonmin", &caps["londir"] == "W")?;
+    Some((latitude, longitude))
+}
+
+/// Corrects a daylight offset whose sign was lost to a typographical error.
+///
+/// Some documents contain "UTC-5( 4DT)", where the space stands in for the minus sign. A daylight
+/// offset of more than +2 hours next to a standard offset of less than -2 hours is implausible, so
+/// the daylight offset is negated; "UTC-5( 4DT)" thereby becomes -5 with -4 during daylight saving
+/// time. Returns `None` if no correction is necessary.
+fn correct_daylight_sign_typo(standard_offset: UtcOffset, daylight_offset: UtcOffset) -> Option<UtcOffset> {
+    if standard_offset.minutes() < -2*60 && daylight_offset.minutes() > 2*60 {
+        Some(UtcOffset::from_minutes(-daylight_offset.minutes()))
+    } else {
+        None
+    }
+}
+
+
+/// Returns the name of the Etc/GMT zone with the given offset from UTC.
+///
+/// Note that the signs of these zones are inverted (POSIX-style), e.g. UTC+1 is "Etc/GMT-1". Only
+/// whole-hour offsets from UTC-12 to UTC+14 have such a zone; for other offsets, a warning is
+/// output and "Etc/UTC" is returned.
+fn etc_gmt_zone(utc_offset: UtcOffset) -> String {
+    match utc_offset.whole_hours() {
+        Some(0) => "Etc/GMT".to_owned(),
+        Some(hours @ 1..=14) => format!("Etc/GMT-{}", hours),
+        Some(hours @ -12..=-1) => format!("Etc/GMT+{}", -hours),
+        _ => {
+            eprintln!("no Etc/GMT zone for UTC{}; using Etc/UTC", utc_offset);
+            "Etc/UTC".to_owned()
+        },
+    }
+}
+
+
+fn load_time_zones(paths: &[PathBuf]) -> TimeZoneConfig {
+    let mut config = TimeZoneConfig::default();
+    for path in paths {
+        merge_time_zone_file(&mut config, path);
+    }
+    config
+}
+
+
+/// Reads the CSV file of --icao-countries, returning the country code of each airport.
+fn load_icao_countries(path: &Path) -> HashMap<String, String> {
+    let csv_string = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("failed to read ICAO country file {}: {}", path.display(), e));
+    let mut icao_countries = HashMap::new();
+    for (line_index, line) in csv_string.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',')
+            .map(|field| field.trim().trim_matches('"'))
+            .collect();
+        if fields.len() < 2 {
+            panic!("{}:{}: expected ICAO code and country code", path.display(), line_index + 1);
+        }
+        let (icao, country) = (fields[0].to_uppercase(), fields[1].to_uppercase());
+        if country_zones(&country).is_none() {
+            eprintln!("{}:{}: unknown country code {:?}", path.display(), line_index + 1, country);
+        }
+        icao_countries.insert(icao, country);
+    }
+    icao_countries
+}
+
 
-    let name_to_timezone: HashMap<String, TimeZoneDefinition> = {
-        let time_zones = std::fs::read_to_string(&opts.time_zones)
-            .expect("failed to read time zone file");
-        toml::from_str(&time_zones)
+/// Reads the time zone file at the given path and merges it over the given configuration.
+fn merge_time_zone_file(config: &mut TimeZoneConfig, path: &Path) {
+    let time_zones_string = match std::fs::read_to_string(path) {
+        Ok(tzs) => tzs,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => panic!(
+            "time zone file {} not found; pass the path to one using --time-zones or run without definitions using --no-config",
+            path.display(),
+        ),
+        Err(e) => panic!("failed to read time zone file {}: {}", path.display(), e),
+    };
+    if time_zones_string.trim().is_empty() {
+        // no definitions (YAML does not consider an empty document to be an empty map)
+        return;
+    }
+    let is_yaml = path.extension()
+        .map(|ext| ext == "yaml" || ext == "yml")
+        .unwrap_or(false);
+    let time_zones: TimeZoneFile = if is_yaml {
+        serde_yaml::from_str(&time_zones_string)
+            .expect("failed to parse time zone file")
+    } else {
+        toml::from_str(&time_zones_string)
             .expect("failed to parse time zone file")
     };
+    if let Some(dst_matching) = time_zones.dst_matching {
+        // later files take precedence
+        config.dst_matching = dst_matching;
+    }
+    if let Some(offset_matching) = time_zones.offset_matching {
+        config.offset_matching = offset_matching;
+    }
+    config.extra_patterns.extend(time_zones.extra_patterns);
+    if let Some(effective) = time_zones.effective {
+        if NaiveDate::parse_from_str(&effective, "%Y-%m-%d").is_err() {
+            panic!("effective date {:?} in {} is not in YYYY-MM-DD format", effective, path.display());
+        }
+        config.effective = Some(effective);
+    }
+    if let Some(column_bands) = time_zones.column_bands {
+        config.column_bands = Some(column_bands);
+    }
+    for (name, definition) in time_zones.definitions {
+        if config.definitions.insert(name.clone(), definition).is_some() {
+            eprintln!("time zone {:?} redefined in {}", name, path.display());
+        }
+    }
+}
 
-    for pdf_path in &opts.pdf_paths {
-        let pdf_file = PdfFileOptions::cached()
-            .open(pdf_path).expect("failed to open PDF file");
-        let top_level_bookmarks = get_top_level_bookmarks(pdf_file.get_root(), &pdf_file);
-        let destination_pages = get_destination_pages(pdf_file.get_root(), &pdf_file);
-        let page_references = get_page_references(pdf_file.get_root(), &pdf_file);
-
-        let airport_directory_bookmark = top_level_bookmarks.iter()
-            .filter(|bkmk| bkmk.title.ends_with(": AIRPORT/FACILITY DIRECTORY"))
-            .nth(0).expect("no airport directory bookmark found");
-        let airport_directory_page = bookmark_destination_to_page_index(
-            &airport_directory_bookmark.destination,
-            &destination_pages,
-            &page_references,
-        )
-            .expect("airport directory page not found");
-
-        let bookmark_after_directory_opt = top_level_bookmarks
-            .get(airport_directory_bookmark.index + 1);
-        let page_after_directory = match bookmark_after_directory_opt {
-            Some(bad) => {
-                bookmark_destination_to_page_index(
-                    &bad.destination,
-                    &destination_pages,
-                    &page_references,
-                )
-                    .expect("page for bookmark after airport directory not found")
-            },
-            None => {
-                // airport directory is the last page
-                pdf_file.num_pages()
-            },
+
+/// A line of text assembled from fragments on the same baseline.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct AssembledLine {
+    /// The position of the leftmost fragment.
+    pub start: Coords,
+    /// The text of the fragments, concatenated without separators; this is what is matched.
+    pub text: String,
+    /// The text of the fragments separated by `--fragment-separator`, for display.
+    pub display_text: String,
+    /// The least trustworthy way in which any of the fragments was decoded.
+    pub decode_path: DecodePath,
+    /// The text split into table cells at large horizontal gaps; empty unless `--cell-gap` is set.
+    pub cells: Vec<String>,
+    /// The byte offset in `text` at which each fragment starts, along with its X coordinate.
+    pub fragment_offsets: Vec<(usize, NoNonsenseF32)>,
+    /// The text of the ICAO and UTC columns given by the column bands, taken from the fragments
+    /// before the text is rewritten; `None` unless column bands are configured and both are
+    /// non-empty.
+    pub band_cells: Option<(String, String)>,
+}
+
+
+/// The information parsed from a line of an airport directory.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct ParsedLine {
+    /// The ICAO code of the airport, in uppercase.
+    pub icao: String,
+    pub utc_standard: UtcOffset,
+    pub utc_daylight: Option<UtcOffset>,
+    /// Whether the daylight saving offset had to be corrected for an error in the document.
+    pub corrected: bool,
+    /// Whether the document only gives the daylight saving offset (e.g. "UTC-4(DT)" in a summer
+    /// edition), in which case `utc_standard` is actually that offset and `utc_daylight` is `None`.
+    pub daylight_only: bool,
+}
+
+
+/// How fragments of a line at the same horizontal position are ordered. Either way, the order is
+/// the same on every run.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, ValueEnum)]
+enum XTieBreak {
+    /// The upper fragment comes first.
+    #[default]
+    Vertical,
+
+    /// The fragment drawn first comes first, which keeps combining marks drawn after their base
+    /// character (but slightly above it) in place.
+    Drawing,
+}
+
+/// How the text between the ICAO code and "UTC" is matched, which matters if a line mentions UTC
+/// more than once.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, ValueEnum)]
+enum GapMatching {
+    /// Take the first UTC offset after the ICAO code. Suits documents such as the FAA Chart
+    /// Supplement, in which the offset follows the airport's name and location and later text
+    /// (e.g. operating hours) may mention UTC again.
+    #[default]
+    Lazy,
+
+    /// Take the last UTC offset on the line. Suits documents in which text between the ICAO code
+    /// and the offset (e.g. a remark in the airport's name column) mentions UTC.
+    Greedy,
+
+    /// Take the UTC offset drawn horizontally nearest to the ICAO code. Suits dense lines which
+    /// mention several offsets (e.g. "local UTC+1, operations UTC+0") in different places.
+    Nearest,
+}
+impl GapMatching {
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            // the nearest offset is moved up front by select_nearest_utc
+            Self::Lazy|Self::Nearest => ".+?",
+            Self::Greedy => ".+",
+        }
+    }
+}
+
+
+/// Compiles the pattern matching a line with an ICAO code and UTC offsets from its parts.
+fn icao_and_utc_regex(gap: GapMatching, utc_pattern: &str) -> Regex {
+    Regex::new(&format!("{}{}{}", ICAO_PATTERN, gap.pattern(), utc_pattern))
+        .unwrap()
+}
+
+
+/// Settings governing how lines are parsed, along with the regular expressions selected by them.
+#[derive(Clone, Copy, Debug)]
+struct LineParsing {
+    /// Whether to work around errors found in actual documents. Enabled if the program has been
+    /// built with the `parsing_hacks` feature and `--strict-parsing` has not been passed.
+    pub hacks: bool,
+
+    /// Whether a comma is the decimal separator.
+    pub decimal_comma: bool,
+
+    /// How the text between the ICAO code and "UTC" is matched.
+    pub gap: GapMatching,
+
+    // selected once for the whole run instead of for every page or line
+    icao_and_utc: &'static Regex,
+    utc: &'static Regex,
+}
+impl LineParsing {
+    pub fn from_opts(opts: &Opts) -> Self {
+        let hacks = cfg!(feature = "parsing_hacks") && !opts.strict_parsing;
+        let icao_and_utc: &'static Regex = match (hacks, opts.gap) {
+            (true, GapMatching::Lazy|GapMatching::Nearest) => &ICAO_AND_UTC_HACKS_LAZY,
+            (true, GapMatching::Greedy) => &ICAO_AND_UTC_HACKS_GREEDY,
+            (false, GapMatching::Lazy|GapMatching::Nearest) => &ICAO_AND_UTC_STRICT_LAZY,
+            (false, GapMatching::Greedy) => &ICAO_AND_UTC_STRICT_GREEDY,
         };
+        let utc: &'static Regex = if hacks { &UTC_HACKS } else { &UTC_STRICT };
+        Self {
+            hacks,
+            decimal_comma: opts.decimal_comma,
+            gap: opts.gap,
+            icao_and_utc,
+            utc,
+        }
+    }
 
-        // run through those pages
-        for page_index in airport_directory_page..page_after_directory {
-            let page = pdf_file.get_page(page_index)
-                .expect("failed to obtain page");
-            let Some(contents) = page.contents.as_ref() else { continue };
-            let ops = contents.operations(&pdf_file)
-                .expect("failed to obtain page ops");
-
-            let fonts: HashMap<&str, &MaybeRef<Font>> = page
-                .resources().expect("page has no resources")
-                .fonts().collect();
-
-            let mut coordinates_to_text = BTreeMap::new();
-            let mut text_matrix = None;
-            let mut current_font = None;
-            for op in ops {
-                match op {
-                    Op::BeginText => {
-                        text_matrix = Some(Matrix2D::default());
-                    },
-                    Op::EndText => {
-                        text_matrix = None;
-                    },
-                    Op::SetTextMatrix { matrix } => {
-                        text_matrix = Some(Matrix2D {
-                            a0: matrix.a.try_into().unwrap(),
-                            a1: matrix.b.try_into().unwrap(),
-                            a2: NoNonsenseF32::zero(),
-
-                            b0: matrix.c.try_into().unwrap(),
-                            b1: matrix.d.try_into().unwrap(),
-                            b2: NoNonsenseF32::zero(),
-
-                            c0: matrix.e.try_into().unwrap(),
-                            c1: matrix.f.try_into().unwrap(),
-                            c2: NoNonsenseF32::one(),
-                        });
-                    },
-                    Op::TextDraw { text } => {
-                        let Some(matrix) = &text_matrix else { continue };
-                        let mut coords = matrix.apply_to_vector(Coords::default());
-                        coords.y = (-f32::from(coords.y)).try_into().unwrap();
-
-                        let Ok(text_string) = text.to_string() else { continue };
-                        coordinates_to_text
-                            .entry(coords)
-                            .or_insert_with(|| String::new())
-                            .push_str(&text_string);
-                    },
-                    Op::TextFont { name, .. } => {
-                        current_font = Some(
-                            *fonts
-                                .get(name.as_str()).expect("unknown font")
-                        );
-                    },
-                    Op::TextDrawAdjusted { array } => {
-                        let Some(matrix) = &text_matrix else { continue };
-                        let mut coords = matrix.apply_to_vector(Coords::default());
-                        coords.y = (-f32::from(coords.y)).try_into().unwrap();
-
-                        for adjustment in array {
-                            match adjustment {
-                                TextDrawAdjusted::Spacing(_spacing) => {},
-                                TextDrawAdjusted::Text(text) => {
-                                    let Some(text_string) = font_decode(current_font, text, &pdf_file) else { continue };
-                                    coordinates_to_text
-                                        .entry(coords)
-                                        .or_insert_with(|| String::new())
-                                        .push_str(&text_string);
-                                },
-                            }
-                        }
-                    },
-                    _other => {
-                        // println!("{:?}", other);
+    /// The regular expression matching lines with an ICAO code and UTC offsets.
+    pub fn icao_and_utc(&self) -> &'static Regex {
+        self.icao_and_utc
+    }
+
+    /// The regular expression matching UTC offsets alone.
+    pub fn utc(&self) -> &'static Regex {
+        self.utc
+    }
+}
+
+
+/// Parses a line containing an ICAO code and the airport's UTC offsets, such as
+/// "(KJFK) ... UTC-5(-4DT)". Returns `None` if the line does not match.
+fn parse_icao_utc_line(line: &str, parsing: LineParsing) -> Option<ParsedLine> {
+    let decimal_comma = parsing.decimal_comma;
+    let caps = parsing.icao_and_utc().captures(line)?;
+    let mut corrected = false;
+    // normalize case for stable output and config matching
+    let icao = caps.name("icao").expect("did not capture icao").as_str().to_uppercase();
+    let offsets = normalize_offset(caps.name("utc").expect("did not capture utc").as_str(), decimal_comma)
+        .and_then(|offset| {
+            let dst_offset = if let Some(d) = caps.name("utcdst") {
+                let doff = normalize_offset(d.as_str(), decimal_comma)?;
+                match correct_daylight_sign_typo(offset, doff) {
+                    Some(corrected_offset) => {
+                        corrected = true;
+                        Some(corrected_offset)
                     },
+                    None => Some(doff),
                 }
-            }
+            } else if let Some(d) = caps.name("dstutc") {
+                corrected = true;
+                Some(normalize_reverse_offset(d.as_str())?)
+            } else if let Some(s) = caps.name("dstshift") {
+                // "UTC+1 (DST +1h)" is a shift, not an absolute offset
+                let shift = normalize_offset(s.as_str(), decimal_comma)?;
+                let dst_offset = offset.checked_add(shift)
+                    .ok_or_else(|| format!("daylight offset UTC{} {} out of range", offset, shift))?;
+                Some(dst_offset)
+            } else {
+                None
+            };
+            Ok((offset, dst_offset))
+        });
+    let (offset, dst_offset) = match offsets {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}: skipping line: {}", icao, e);
+            return None;
+        },
+    };
+    Some(ParsedLine {
+        icao,
+        utc_standard: offset,
+        utc_daylight: dst_offset,
+        corrected,
+        daylight_only: caps.name("dtonly").is_some(),
+    })
+}
 
-            // assemble lines
-            let mut lines = BTreeMap::new();
-            for (coordinates, text) in &coordinates_to_text {
-                let line = lines
-                    .entry(coordinates.y)
-                    .or_insert_with(|| String::new());
-                line.push_str(text);
-            }
-            for line in lines.values() {
-                if let Some(caps) = ICAO_AND_UTC.captures(line) {
-                    let icao = caps.name("icao").expect("did not capture icao").as_str();
-                    let offset = normalize_offset(caps.name("utc").expect("did not capture utc").as_str());
-                    let dst_offset = caps.name("utcdst")
-                        .map(|d| normalize_offset(d.as_str()))
-                        // handle typographical error "UTC-5( 4DT)"
-                        .map(|doff| if offset < -2 && doff > 2 { -doff } else { doff })
-                        .or_else(|| caps.name("dstutc").map(|d| normalize_reverse_offset(d.as_str())));
-
-                    // match timezone
-                    let mut iana_timezone_opt = None;
-                    for timezone in name_to_timezone.values() {
-                        if let Some(icao_match) = timezone.icao_match.as_ref() {
-                            if !icao_match.0.is_match(icao) {
-                                continue;
-                            }
-                        }
-                        if offset == timezone.utc_standard && dst_offset == timezone.utc_daylight {
-                            iana_timezone_opt = Some(timezone.iana.clone());
-                            break;
-                        }
-                    }
 
-                    if let Some(iana_timezone) = iana_timezone_opt.as_ref() {
-                        println!("{} {}", icao, iana_timezone);
-                    } else {
-                        println!("{} ?", icao);
-                    }
-                }
-            }
+/// Parses the cells of a line, falling back to the whole line if there is only one cell or no cell
+/// matches (e.g. because the ICAO code and the offset are in different cells).
+fn parse_line_or_cells(line: &str, cells: &[String], parsing: LineParsing) -> Vec<ParsedLine> {
+    if cells.len() > 1 {
+        let cell_matches: Vec<ParsedLine> = cells.iter()
+            .filter_map(|cell| parse_icao_utc_line(cell, parsing))
+            .collect();
+        if !cell_matches.is_empty() {
+            return cell_matches;
         }
     }
+    parse_icao_utc_line(line, parsing)
+        .into_iter()
+        .collect()
+}
+
+
+/// Parses the ICAO code and UTC offsets from the columns of a table row given by the column bands.
+///
+/// The offset column may contain the offsets with or without the "UTC" prefix (e.g. "-5(-4DT)").
+fn parse_column_cells(line: &AssembledLine, parsing: LineParsing) -> Option<ParsedLine> {
+    let (icao_cell, utc_cell) = line.band_cells.as_ref()?;
+    let icao = icao_cell.trim_start_matches('(').trim_end_matches(')');
+    let utc_cell = if parsing.utc().is_match(utc_cell) {
+        utc_cell.clone()
+    } else {
+        format!("UTC{}", utc_cell)
+    };
+    // reassemble the cells into the form of a directory line
+    parse_icao_utc_line(&format!("({}) {}", icao, utc_cell), parsing)
+}
+
+
+/// With `GapMatching::Nearest`, if the line mentions several UTC offsets after the ICAO code,
+/// returns the line with the text between the ICAO code and the offset drawn horizontally nearest
+/// to it removed, so that matching picks that offset. Offsets within the same fragment are equally
+/// near, in which case the first one is kept.
+fn select_nearest_utc<'a>(line: &'a str, fragment_offsets: &[(usize, NoNonsenseF32)], parsing: LineParsing) -> Cow<'a, str> {
+    if parsing.gap != GapMatching::Nearest || fragment_offsets.is_empty() {
+        return Cow::Borrowed(line);
+    }
+    let Some(icao) = ICAO.find(line) else { return Cow::Borrowed(line) };
+    let utc_starts: Vec<usize> = parsing.utc().find_iter(&line[icao.end()..])
+        .map(|m| icao.end() + m.start())
+        .collect();
+    if utc_starts.len() < 2 {
+        return Cow::Borrowed(line);
+    }
+
+    // the X coordinate of the fragment containing the given byte offset
+    let x_at = |offset: usize| -> f32 {
+        let fragment_index = fragment_offsets.partition_point(|(start, _)| *start <= offset);
+        f32::from(fragment_offsets[fragment_index.saturating_sub(1)].1)
+    };
+    let icao_x = x_at(icao.start());
+    let nearest_start = utc_starts.iter()
+        .copied()
+        .min_by(|a, b| (x_at(*a) - icao_x).abs().total_cmp(&(x_at(*b) - icao_x).abs()))
+        .unwrap();
+    if nearest_start == utc_starts[0] {
+        Cow::Borrowed(line)
+    } else {
+        Cow::Owned(format!("{} {}", &line[..icao.end()], &line[nearest_start..]))
+    }
+}
+
+
+/// Collapses each run of whitespace and control characters into a single space and trims the
+/// string.
+fn normalize_whitespace(text: &str) -> String {
+    text.split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|piece| !piece.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+
+/// Runs the command using the system shell, passing the input on standard input and returning
+/// the standard output.
+fn run_shell_command(command: &str, input: String) -> String {
+    let mut shell = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut sh = Command::new("sh");
+        sh.arg("-c");
+        sh
+    };
+    let mut child = shell
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| panic!("failed to run preprocessing command {:?}: {}", command, e));
+
+    // write from another thread so that a command producing output early cannot deadlock us
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()
+        .unwrap_or_else(|e| panic!("failed to run preprocessing command {:?}: {}", command, e));
+    writer.join()
+        .unwrap()
+        .unwrap_or_else(|e| panic!("failed to pass lines to preprocessing command {:?}: {}", command, e));
+    if !output.status.success() {
+        panic!("preprocessing command {:?} failed: {}", command, output.status);
+    }
+    String::from_utf8(output.stdout)
+        .unwrap_or_else(|_| panic!("preprocessing command {:?} output invalid UTF-8", command))
+}
+
+
+/// Replaces the text of the lines by the output of the preprocessing command. The fragment offsets,
+/// cells and column band cells no longer apply to a changed text and are cleared, so that it is
+/// parsed as a whole. Returns the number of lines whose column band cells were cleared.
+fn preprocess_lines(command: &str, per_line: bool, lines: &mut [AssembledLine]) -> usize {
+    if lines.is_empty() {
+        return 0;
+    }
+    let new_texts: Vec<String> = if per_line {
+        lines.iter()
+            .map(|line| run_shell_command(command, format!("{}\n", line.text)).trim_end_matches(['\r', '\n']).to_owned())
+            .collect()
+    } else {
+        let mut input = String::new();
+        for line in lines.iter() {
+            input.push_str(&line.text);
+            input.push('\n');
+        }
+        let output = run_shell_command(command, input);
+        let output_lines: Vec<String> = output.lines().map(|l| l.to_owned()).collect();
+        if output_lines.len() != lines.len() {
+            panic!(
+                "preprocessing command {:?} turned {} lines into {}; it must output one line per input line",
+                command, lines.len(), output_lines.len(),
+            );
+        }
+        output_lines
+    };
+    let mut cleared_band_cells = 0;
+    for (line, new_text) in lines.iter_mut().zip(new_texts) {
+        if line.text != new_text {
+            line.text = new_text;
+            line.fragment_offsets.clear();
+            line.cells.clear();
+            if line.band_cells.take().is_some() {
+                cleared_band_cells += 1;
+            }
+        }
+    }
+    cleared_band_cells
+}
+
+
+/// Joins a line that ends before the UTC offset, such as "(KJFK) ... UTC", with the following line,
+/// onto which the offset has wrapped in a narrow column. Only done with parsing hacks; if the joined
+/// line yields an airport, the following line is not parsed on its own and the confidence is
+/// lowered as for the other corrections.
+fn join_wrapped_offset<'a>(line: &'a str, next_line: Option<&str>, parsing: LineParsing) -> Cow<'a, str> {
+    if !parsing.hacks {
+        return Cow::Borrowed(line);
+    }
+    match next_line {
+        Some(next) if !parsing.icao_and_utc().is_match(line) && ICAO_AND_UTC_WRAPPED.is_match(line) => {
+            Cow::Owned(format!("{}{}", line, next))
+        },
+        _ => Cow::Borrowed(line),
+    }
+}
+
+
+/// Applies the extra patterns to the line. Each named capture group that participates in a match
+/// yields a field of the same name; a pattern without named groups yields a field named after the
+/// pattern containing the whole match.
+fn extract_extra_fields(line: &str, extra_patterns: &HashMap<String, SerializableRegex>) -> BTreeMap<String, String> {
+    let mut extra = BTreeMap::new();
+    for (pattern_name, pattern) in extra_patterns {
+        let Some(caps) = pattern.0.captures(line) else { continue };
+        let mut has_named_groups = false;
+        for group_name in pattern.0.capture_names().flatten() {
+            has_named_groups = true;
+            if let Some(value) = caps.name(group_name) {
+                extra.insert(group_name.to_owned(), value.as_str().to_owned());
+            }
+        }
+        if !has_named_groups {
+            extra.insert(pattern_name.clone(), caps[0].to_owned());
+        }
+    }
+    extra
+}
+
+
+/// Serializes each time zone definition and extra pattern to TOML and YAML and parses it again,
+/// reporting those that change in the process (e.g. a regular expression that does not reparse).
+/// Returns the number of definitions and patterns that do not survive the round trip.
+fn validate_roundtrip(config: &TimeZoneConfig) -> usize {
+    fn roundtrip<T: PartialEq + Serialize + for<'de> Deserialize<'de>>(value: &T) -> Result<(), String> {
+        let toml_string = toml::to_string(value)
+            .map_err(|e| format!("failed to serialize to TOML: {}", e))?;
+        let toml_value: T = toml::from_str(&toml_string)
+            .map_err(|e| format!("failed to reparse TOML {:?}: {}", toml_string, e))?;
+        if &toml_value != value {
+            return Err(format!("TOML {:?} parses into a different value", toml_string));
+        }
+
+        let yaml_string = serde_yaml::to_string(value)
+            .map_err(|e| format!("failed to serialize to YAML: {}", e))?;
+        let yaml_value: T = serde_yaml::from_str(&yaml_string)
+            .map_err(|e| format!("failed to reparse YAML {:?}: {}", yaml_string, e))?;
+        if &yaml_value != value {
+            return Err(format!("YAML {:?} parses into a different value", yaml_string));
+        }
+        Ok(())
+    }
+
+    // a definition constructed in code from the same parts (recompiling its pattern) must be equal
+    fn rebuild(definition: &TimeZoneDefinition) -> Result<(), String> {
+        let mut rebuilt = TimeZoneDefinition::new(definition.iana.as_str(), definition.utc_standard);
+        if let Some(utc_daylight) = definition.utc_daylight {
+            rebuilt = rebuilt.with_utc_daylight(utc_daylight);
+        }
+        if let Some(icao_match) = definition.icao_match.as_ref() {
+            rebuilt = rebuilt.with_icao_match(icao_match.0.as_str())
+                .map_err(|e| format!("pattern {:?} does not recompile: {}", icao_match.0.as_str(), e))?;
+        }
+        if &rebuilt != definition {
+            return Err("rebuilding the definition from its parts yields a different value".to_owned());
+        }
+        Ok(())
+    }
+
+    let mut mismatches = 0;
+    let sorted_definitions: BTreeMap<_, _> = config.definitions.iter().collect();
+    for (name, definition) in sorted_definitions {
+        if let Err(e) = roundtrip(definition) {
+            eprintln!("time zone {:?}: {}", name, e);
+            mismatches += 1;
+        } else if let Err(e) = rebuild(definition) {
+            eprintln!("time zone {:?}: {}", name, e);
+            mismatches += 1;
+        }
+    }
+    let sorted_patterns: BTreeMap<_, _> = config.extra_patterns.iter().collect();
+    for (name, pattern) in sorted_patterns {
+        // wrapped in a map as TOML documents must be tables
+        let wrapped: BTreeMap<String, SerializableRegex> = [(name.clone(), pattern.clone())].into();
+        if let Err(e) = roundtrip(&wrapped) {
+            eprintln!("extra pattern {:?}: {}", name, e);
+            mismatches += 1;
+        }
+    }
+    mismatches
+}
+
+
+/// Finds the pages of the airport directory using the document's bookmarks (or the named
+/// destination passed via `--start-dest`).
+fn find_directory_pages<OC, SC>(
+    pdf_file: &PdfFile<Vec<u8>, OC, SC>,
+    opts: &Opts,
+) -> BTreeSet<u32> where
+    OC: PdfCache<Result<AnySync, Arc<PdfError>>>,
+    SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let top_level_bookmarks = get_top_level_bookmarks(pdf_file.get_root(), pdf_file, opts.quiet);
+    let destination_pages = get_destination_pages(pdf_file.get_root(), pdf_file);
+    let page_references = get_page_references(pdf_file.get_root(), pdf_file);
+
+    // the airport directory may be split into multiple parts
+    let mut directory_ranges = Vec::new();
+    if let Some(start_dest) = opts.start_dest.as_ref() {
+        let start_page = bookmark_destination_to_page_index(
+            &BookmarkDestination::Named(start_dest.clone()),
+            &destination_pages,
+            &page_references,
+        )
+            .unwrap_or_else(|| {
+                eprintln!(
+                    "start destination {:?} is neither a named destination nor a page number of the document",
+                    start_dest,
+                );
+                std::process::exit(1);
+            });
+
+        // the directory ends at the next bookmark after the start page
+        let end_page = top_level_bookmarks.iter()
+            .filter_map(|bkmk| bookmark_destination_to_page_index(
+                &bkmk.destination,
+                &destination_pages,
+                &page_references,
+            ))
+            .filter(|page| *page > start_page)
+            .min()
+            .unwrap_or_else(|| pdf_file.num_pages());
+        directory_ranges.push(start_page..end_page);
+    } else {
+        let airport_directory_bookmarks = top_level_bookmarks.iter()
+            .filter(|bkmk| bkmk.title.ends_with(": AIRPORT/FACILITY DIRECTORY"));
+        for airport_directory_bookmark in airport_directory_bookmarks {
+            let airport_directory_page = bookmark_destination_to_page_index(
+                &airport_directory_bookmark.destination,
+                &destination_pages,
+                &page_references,
+            )
+                .unwrap_or_else(|| panic!(
+                    "airport directory page not found ({})",
+                    airport_directory_bookmark.destination,
+                ));
+
+            let bookmark_after_directory_opt = top_level_bookmarks
+                .get(airport_directory_bookmark.index + 1);
+            let page_after_directory = match bookmark_after_directory_opt {
+                Some(bad) => {
+                    bookmark_destination_to_page_index(
+                        &bad.destination,
+                        &destination_pages,
+                        &page_references,
+                    )
+                        .unwrap_or_else(|| panic!(
+                            "page for bookmark after airport directory not found ({})",
+                            bad.destination,
+                        ))
+                },
+                None => {
+                    // airport directory is the last page
+                    pdf_file.num_pages()
+                },
+            };
+            let page_after_directory = if page_after_directory == airport_directory_page {
+                // the next part starts on the same page; the directory only takes up part of it
+                airport_directory_page + 1
+            } else if page_after_directory < airport_directory_page {
+                eprintln!(
+                    "the bookmark after {:?} points before it (page {} < {}); scanning to the end of the document",
+                    airport_directory_bookmark.title, page_after_directory, airport_directory_page,
+                );
+                pdf_file.num_pages()
+            } else {
+                page_after_directory
+            };
+            directory_ranges.push(airport_directory_page..page_after_directory);
+        }
+        if directory_ranges.is_empty() {
+            panic!("no airport directory bookmark found");
+        }
+    }
+
+    // each page only once, even if ranges overlap
+    directory_ranges.into_iter()
+        .flatten()
+        .collect()
+}
+
+
+/// Extracts the time zones of the airports on the page with the given index, invoking the callback
+/// for each airport found.
+fn extract_airport_timezones_from_page<OC, SC>(
+    pdf_file: &PdfFile<Vec<u8>, OC, SC>,
+    document_name: &str,
+    page_index: u32,
+    opts: &Opts,
+    parsing: LineParsing,
+    time_zones: &TimeZoneConfig,
+    callback: &mut dyn FnMut(AirportResult),
+) -> ExtractionStats where
+    OC: PdfCache<Result<AnySync, Arc<PdfError>>>,
+    SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let page = pdf_file.get_page(page_index)
+        .expect("failed to obtain page");
+    let Some(contents) = page.contents.as_ref() else { return ExtractionStats::default() };
+    let mut stats = ExtractionStats {
+        pages_processed: 1,
+        ..ExtractionStats::default()
+    };
+    let ops = contents.operations(pdf_file)
+        .expect("failed to obtain page ops");
+
+    let fonts = get_page_fonts(&page);
+    let selects_font = ops.iter().any(|op| matches!(op, Op::TextFont { .. }));
+    if fonts.is_empty() && selects_font && !opts.quiet {
+        // text shown with Tj is read without decoding it using the font; that shown with TJ is lost
+        eprintln!("page {} has no font resources; only text shown with Tj is read", page_index);
+    }
+    if opts.dump_fonts {
+        let sorted_fonts: BTreeMap<_, _> = fonts.iter().collect();
+        for (name, font) in sorted_fonts {
+            eprintln!("page {}: font {}: {}", page_index, name, describe_font_encoding(font, pdf_file));
+        }
+    }
+
+    // (left, bottom, right, top)
+    let bounds = opts.bounds_margin.map(|margin| {
+        let media_box = page.media_box()
+            .expect("failed to obtain page MediaBox");
+        (
+            media_box.left.min(media_box.right) - margin,
+            media_box.bottom.min(media_box.top) - margin,
+            media_box.left.max(media_box.right) + margin,
+            media_box.bottom.max(media_box.top) + margin,
+        )
+    });
+    let out_of_bounds = |coords: Coords| {
+        let Some((left, bottom, right, top)) = bounds else { return false };
+        let (x, y) = (f32::from(coords.x), f32::from(coords.y));
+        let outside = x < left || x > right || y < bottom || y > top;
+        if outside && !opts.quiet {
+            eprintln!("page {}: dropping text placed outside the page at ({}, {})", page_index, x, y);
+        }
+        outside
+    };
+
+    // coordinates are normalized into 0..1 from the top left of the page so that the line
+    // tolerance is independent of the page size
+    let media_box = page.media_box().ok();
+    let page_rotation = if opts.honor_rotation {
+        page.rotate.rem_euclid(360)
+    } else {
+        0
+    };
+    if media_box.is_none() {
+        eprintln!("page {} has no MediaBox; not normalizing coordinates", page_index);
+    }
+    let normalize_coords = |coords: Coords| {
+        let (x, y) = (f32::from(coords.x), f32::from(coords.y));
+        let (x, y) = match media_box {
+            Some(mb) if mb.right != mb.left && mb.top != mb.bottom => {
+                let (left, top) = (mb.left.min(mb.right), mb.bottom.max(mb.top));
+                let (x, y) = ((x - left) / (mb.right - mb.left).abs(), (top - y) / (mb.top - mb.bottom).abs());
+                // the page is displayed rotated clockwise
+                match page_rotation {
+                    90 => (1.0 - y, x),
+                    180 => (1.0 - x, 1.0 - y),
+                    270 => (y, 1.0 - x),
+                    _ => (x, y),
+                }
+            },
+            // at least make y increase from top to bottom
+            _ => (x, -y),
+        };
+        Coords {
+            x: x.try_into().unwrap(),
+            y: y.try_into().unwrap(),
+        }
+    };
+
+    let mut coordinates_to_text = BTreeMap::new();
+    let mut ocr_text = BTreeMap::new();
+    // the order in which text was first drawn at each position, for --x-tie-break drawing
+    let mut drawing_order: HashMap<Coords, usize> = HashMap::new();
+    let mut text_invisible = false;
+    let mut text_matrix = None;
+    let mut text_line_matrix = None;
+    let mut text_leading = 0.0;
+    let mut current_font = None;
+    let mut current_font_size = 0.0;
+    let mut current_font_vertical = false;
+    let mut vertical_fonts_reported = HashSet::new();
+    let mut current_font_name = String::new();
+    let mut undecoded_by_font: BTreeMap<String, (usize, BTreeSet<u16>)> = BTreeMap::new();
+    // marked content IDs, for assembling lines from the structure tree
+    let mut marked_content_ids: Vec<Option<i32>> = Vec::new();
+    let mut marked_text: HashMap<i32, (Coords, String, DecodePath)> = HashMap::new();
+    let mut add_marked_text = |marked_content_ids: &[Option<i32>], coords: Coords, text: &str, decode_path: DecodePath| {
+        if !opts.use_struct_tree {
+            return;
+        }
+        let Some(Some(mcid)) = marked_content_ids.last() else { return };
+        let (_, marked, marked_path) = marked_text
+            .entry(*mcid)
+            .or_insert_with(|| (coords, String::new(), DecodePath::ToUnicode));
+        marked.push_str(text);
+        *marked_path = (*marked_path).min(decode_path);
+    };
+    for op in ops {
+        match op {
+            Op::BeginMarkedContent { properties, .. } => {
+                // marked content without an ID belongs to the enclosing marked content
+                let inherited = marked_content_ids.last().copied().flatten();
+                let mcid = match properties {
+                    Some(Primitive::Dictionary(properties)) => properties.get("MCID")
+                        .and_then(|mcid| mcid.as_integer().ok()),
+                    _ => None,
+                };
+                marked_content_ids.push(mcid.or(inherited));
+            },
+            Op::EndMarkedContent => {
+                marked_content_ids.pop();
+            },
+            Op::BeginText => {
+                text_matrix = Some(Matrix2D::default());
+                text_line_matrix = text_matrix;
+            },
+            Op::EndText => {
+                text_matrix = None;
+                text_line_matrix = None;
+            },
+            Op::SetTextMatrix { matrix } => {
+                // sets both the text matrix and the text line matrix
+                text_matrix = Some(Matrix2D {
+                    a0: matrix.a.try_into().unwrap(),
+                    a1: matrix.b.try_into().unwrap(),
+                    a2: NoNonsenseF32::zero(),
+
+                    b0: matrix.c.try_into().unwrap(),
+                    b1: matrix.d.try_into().unwrap(),
+                    b2: NoNonsenseF32::zero(),
+
+                    c0: matrix.e.try_into().unwrap(),
+                    c1: matrix.f.try_into().unwrap(),
+                    c2: NoNonsenseF32::one(),
+                });
+                text_line_matrix = text_matrix;
+            },
+            Op::Leading { leading } => {
+                text_leading = leading;
+            },
+            Op::MoveTextPosition { translation } => {
+                // offsets are relative to the start of the current line, not the current position
+                let Some(line_matrix) = &text_line_matrix else { continue };
+                let moved = line_matrix.multiply(&Matrix2D::translation(translation.x, translation.y));
+                text_matrix = Some(moved);
+                text_line_matrix = Some(moved);
+            },
+            Op::TextNewline => {
+                let Some(line_matrix) = &text_line_matrix else { continue };
+                let moved = line_matrix.multiply(&Matrix2D::translation(0.0, -text_leading));
+                text_matrix = Some(moved);
+                text_line_matrix = Some(moved);
+            },
+            Op::TextDraw { text } => {
+                let Some(matrix) = &text_matrix else { continue };
+                if current_font_vertical {
+                    // our line assembly assumes horizontal text
+                    continue;
+                }
+                if !opts.text_layer.includes(text_invisible) {
+                    continue;
+                }
+                if opts.skip_rotated && matrix.is_rotated_on_page(page_rotation) {
+                    continue;
+                }
+                if matrix.effective_font_size(current_font_size) < opts.min_font_size {
+                    continue;
+                }
+                let coords = matrix.apply_to_vector(Coords::default());
+                if out_of_bounds(coords) {
+                    continue;
+                }
+                let coords = normalize_coords(coords);
+
+                let Ok(text_string) = text.to_string() else {
+                    stats.decode_failures += text.as_bytes().len();
+                    continue;
+                };
+                add_marked_text(&marked_content_ids, coords, &text_string, DecodePath::Raw);
+                let next_order = drawing_order.len();
+                drawing_order.entry(coords).or_insert(next_order);
+                let layer_text = if text_invisible { &mut ocr_text } else { &mut coordinates_to_text };
+                let (fragment, fragment_path) = layer_text
+                    .entry(coords)
+                    .or_insert_with(|| (String::new(), DecodePath::ToUnicode));
+                fragment.push_str(&text_string);
+                *fragment_path = DecodePath::Raw;
+            },
+            Op::TextFont { name, size } => {
+                current_font = fonts.get(name.as_str()).copied();
+                current_font_name = name.as_str().to_owned();
+                current_font_size = size;
+                if current_font.is_none() && !fonts.is_empty() {
+                    eprintln!("page {} references unknown font {:?}", page_index, name.as_str());
+                }
+                current_font_vertical = current_font.is_some_and(|f| is_vertical_font(f));
+                if current_font_vertical && vertical_fonts_reported.insert(name.clone()) {
+                    eprintln!("page {}: skipping text in vertical writing mode font {:?}", page_index, name.as_str());
+                }
+            },
+            Op::TextDrawAdjusted { array } => {
+                let Some(matrix) = &text_matrix else { continue };
+                if current_font_vertical {
+                    // our line assembly assumes horizontal text
+                    continue;
+                }
+                if !opts.text_layer.includes(text_invisible) {
+                    continue;
+                }
+                if opts.skip_rotated && matrix.is_rotated_on_page(page_rotation) {
+                    continue;
+                }
+                if matrix.effective_font_size(current_font_size) < opts.min_font_size {
+                    continue;
+                }
+                let coords = matrix.apply_to_vector(Coords::default());
+                if out_of_bounds(coords) {
+                    continue;
+                }
+                let coords = normalize_coords(coords);
+
+                for adjustment in array {
+                    match adjustment {
+                        TextDrawAdjusted::Spacing(_spacing) => {},
+                        TextDrawAdjusted::Text(text) => {
+                            let text_codes: Vec<u16> = if opts.report_undecoded {
+                                text.as_bytes().iter().map(|b| (*b).into()).collect()
+                            } else {
+                                Vec::new()
+                            };
+                            let code_count = text.as_bytes().len();
+                            let decoded_opt = font_decode(current_font, text, pdf_file, opts.quiet);
+                            stats.decode_failures += decoded_opt.as_ref()
+                                .map_or(code_count, |decoded| decoded.undecoded.len());
+                            if opts.report_undecoded {
+                                let undecoded = match decoded_opt.as_ref() {
+                                    Some(decoded) => &decoded.undecoded,
+                                    // the whole text is lost
+                                    None => &text_codes,
+                                };
+                                if !undecoded.is_empty() {
+                                    let (count, samples) = undecoded_by_font
+                                        .entry(current_font_name.clone())
+                                        .or_insert_with(|| (0, BTreeSet::new()));
+                                    *count += undecoded.len();
+                                    for code in undecoded {
+                                        if samples.len() < 8 {
+                                            samples.insert(*code);
+                                        }
+                                    }
+                                }
+                            }
+                            let Some(decoded) = decoded_opt else { continue };
+                            add_marked_text(&marked_content_ids, coords, &decoded.text, decoded.path);
+                            let next_order = drawing_order.len();
+                            drawing_order.entry(coords).or_insert(next_order);
+                            let layer_text = if text_invisible { &mut ocr_text } else { &mut coordinates_to_text };
+                            let (fragment, fragment_path) = layer_text
+                                .entry(coords)
+                                .or_insert_with(|| (String::new(), DecodePath::ToUnicode));
+                            fragment.push_str(&decoded.text);
+                            *fragment_path = (*fragment_path).min(decoded.path);
+                        },
+                    }
+                }
+            },
+            Op::TextRenderMode { mode } => {
+                // invisible text is usually an OCR layer on top of a scan
+                text_invisible = mode == TextMode::Invisible;
+            },
+            _other => {
+                // println!("{:?}", other);
+            },
+        }
+    }
+
+    // merge the OCR layer into the visible one, dropping text present in both
+    for (coords, (text, decode_path)) in ocr_text {
+        let (fragment, fragment_path) = coordinates_to_text
+            .entry(coords)
+            .or_insert_with(|| (String::new(), DecodePath::ToUnicode));
+        if fragment != &text {
+            fragment.push_str(&text);
+            *fragment_path = (*fragment_path).min(decode_path);
+        }
+    }
+
+    for (font_name, (count, samples)) in &undecoded_by_font {
+        let sample_strings: Vec<String> = samples.iter()
+            .map(|code| format!("0x{:02X}", code))
+            .collect();
+        eprintln!(
+            "page {}: font {:?}: {} characters not decoded (including {})",
+            page_index, font_name, count, sample_strings.join(", "),
+        );
+    }
+
+    if let Some(svg_dir) = opts.dump_svg.as_ref() {
+        let (width, height) = match media_box {
+            Some(mb) if page_rotation == 90 || page_rotation == 270 => ((mb.top - mb.bottom).abs(), (mb.right - mb.left).abs()),
+            Some(mb) => ((mb.right - mb.left).abs(), (mb.top - mb.bottom).abs()),
+            None => (1.0, 1.0),
+        };
+        let svg_path = svg_dir.join(format!("{}.page{}.svg", document_name, page_index));
+        std::fs::write(&svg_path, page_text_svg(&coordinates_to_text, width, height, media_box.is_some()))
+            .unwrap_or_else(|e| panic!("failed to write {}: {}", svg_path.display(), e));
+    }
+
+    // assemble lines
+    // (fragments are ordered left to right, so a sign drawn as a separate text run ends up
+    // in front of its digits even if it was drawn after them)
+    let mut page_results = Vec::new();
+    let mut line_groups: Vec<Vec<_>> = Vec::new();
+    let mut group_start: Option<&Coords> = None;
+    for entry in &coordinates_to_text {
+        match group_start {
+            Some(first) if entry.0.vertical_distance(first) <= opts.line_tolerance => {},
+            _ => {
+                line_groups.push(Vec::new());
+                group_start = Some(entry.0);
+            },
+        }
+        line_groups.last_mut().unwrap().push(entry);
+    }
+    let mut assembled_lines = Vec::with_capacity(line_groups.len());
+    for mut group in line_groups {
+        let line_y = group[0].0.y;
+        match opts.x_tie_break {
+            XTieBreak::Vertical => group.sort_by_key(|(coordinates, _)| (coordinates.x, coordinates.y)),
+            XTieBreak::Drawing => group.sort_by_key(|(coordinates, _)| (coordinates.x, drawing_order.get(coordinates))),
+        }
+        let mut assembled = AssembledLine {
+            start: Coords { y: line_y, x: group[0].0.x },
+            text: String::new(),
+            display_text: String::new(),
+            decode_path: DecodePath::ToUnicode,
+            cells: Vec::new(),
+            fragment_offsets: Vec::new(),
+            band_cells: None,
+        };
+        let mut previous: Option<&Coords> = None;
+        for (coordinates, (text, decode_path)) in group {
+            assembled.fragment_offsets.push((assembled.text.len(), coordinates.x));
+            assembled.text.push_str(text);
+            if previous.is_some() {
+                assembled.display_text.push_str(&opts.fragment_separator);
+            }
+            assembled.display_text.push_str(text);
+            assembled.decode_path = assembled.decode_path.min(*decode_path);
+
+            // glyph widths are unknown, so gaps are measured between the starts of fragments
+            if let Some(cell_gap) = opts.cell_gap {
+                if previous.is_none_or(|p| coordinates.horizontal_distance(p) > cell_gap) {
+                    assembled.cells.push(String::new());
+                }
+                assembled.cells.last_mut().unwrap().push_str(text);
+            }
+            previous = Some(coordinates);
+        }
+        assembled_lines.push(assembled);
+    }
+    if opts.use_struct_tree && !marked_text.is_empty() {
+        let tree_lines = assemble_struct_tree_lines(pdf_file, page_index, &marked_text, opts);
+        if tree_lines.is_empty() {
+            eprintln!("page {}: no tagged text found in the structure tree; using text positions", page_index);
+        } else {
+            assembled_lines = tree_lines;
+        }
+    }
+    if let Some(column_bands) = time_zones.column_bands.as_ref() {
+        // before the text is rewritten, which invalidates the fragment offsets
+        for assembled in &mut assembled_lines {
+            assembled.band_cells = column_bands.cells(assembled);
+        }
+    }
+    if opts.normalize_whitespace {
+        for assembled in &mut assembled_lines {
+            let normalized = normalize_whitespace(&assembled.text);
+            if normalized != assembled.text {
+                assembled.text = normalized;
+                // the offsets no longer apply
+                assembled.fragment_offsets.clear();
+            }
+            for cell in &mut assembled.cells {
+                *cell = normalize_whitespace(cell);
+            }
+            if let Some((icao_cell, utc_cell)) = assembled.band_cells.as_mut() {
+                *icao_cell = normalize_whitespace(icao_cell);
+                *utc_cell = normalize_whitespace(utc_cell);
+            }
+        }
+    }
+    if opts.dump_lines {
+        for assembled in &assembled_lines {
+            eprintln!("page {}: {}", page_index, assembled.display_text);
+        }
+    }
+    if let Some(command) = opts.preprocess.as_ref() {
+        let cleared_band_cells = preprocess_lines(command, opts.preprocess_per_line, &mut assembled_lines);
+        if cleared_band_cells > 0 {
+            eprintln!(
+                "page {}: skipping {} table rows changed by the preprocessing command, as the column bands no longer apply to them",
+                page_index, cleared_band_cells,
+            );
+        }
+    }
+    stats.lines_assembled = assembled_lines.len();
+    let mut skip_line = false;
+    for (line_index, assembled) in assembled_lines.iter().enumerate() {
+        if std::mem::take(&mut skip_line) {
+            // already parsed as the continuation of the previous line
+            continue;
+        }
+        let next_line = assembled_lines.get(line_index + 1);
+        let line = join_wrapped_offset(&assembled.text, next_line.map(|next| next.text.as_str()), parsing);
+        let joined = matches!(line, Cow::Owned(_));
+        let line_path = match next_line {
+            Some(next) if joined => assembled.decode_path.min(next.decode_path),
+            _ => assembled.decode_path,
+        };
+        let parse_target = select_nearest_utc(&line, &assembled.fragment_offsets, parsing);
+        let parsed_lines = if time_zones.column_bands.is_some() {
+            parse_column_cells(assembled, parsing)
+                .into_iter()
+                .collect()
+        } else {
+            parse_line_or_cells(&parse_target, &assembled.cells, parsing)
+        };
+        if joined && !parsed_lines.is_empty() {
+            skip_line = true;
+        }
+        for parsed in parsed_lines {
+            stats.regex_hits += 1;
+            let mut confidence = Confidence::from_decode_path(line_path);
+            // joining a wrapped line is a correction of the layout like the others
+            if parsed.corrected || joined {
+                confidence = confidence.downgrade();
+            }
+            let ParsedLine { icao, utc_standard: offset, utc_daylight: dst_offset, daylight_only, .. } = parsed;
+
+            // implausible offsets are probably garbled captures
+            let out_of_range = |o: UtcOffset| o < opts.min_offset || o > opts.max_offset;
+            if out_of_range(offset) || dst_offset.is_some_and(out_of_range) {
+                eprintln!(
+                    "{}: skipping offsets outside UTC{}..UTC{}: {:?}",
+                    icao, opts.min_offset, opts.max_offset, line,
+                );
+                continue;
+            }
+
+            // match timezone
+            let mut iana_timezone_opt = None;
+            let offset_allowed = time_zones.offset_matching.allows(offset)
+                && dst_offset.is_none_or(|o| time_zones.offset_matching.allows(o));
+            if !offset_allowed {
+                eprintln!(
+                    "{}: offsets are not multiples of 15 minutes (local mean time?); leaving unmatched unless offset_matching = \"raw-minutes\": {:?}",
+                    icao, line,
+                );
+            }
+            let (mut offset, mut dst_offset) = (offset, dst_offset);
+            for timezone in time_zones.definitions.values().filter(|_| offset_allowed) {
+                if let Some(icao_match) = timezone.icao_match.as_ref() {
+                    if !icao_match.0.is_match(&icao) {
+                        continue;
+                    }
+                }
+                if daylight_only {
+                    // only the daylight offset is known; take the standard offset from the definition
+                    if timezone.utc_daylight == Some(offset) {
+                        iana_timezone_opt = Some(timezone.iana.clone());
+                        (offset, dst_offset) = (timezone.utc_standard, Some(offset));
+                        break;
+                    }
+                    continue;
+                }
+                let dst_matches = time_zones.dst_matching.matches(dst_offset, timezone.utc_daylight);
+                if offset == timezone.utc_standard && dst_matches {
+                    iana_timezone_opt = Some(timezone.iana.clone());
+                    break;
+                }
+            }
+            if daylight_only && iana_timezone_opt.is_none() {
+                // the standard offset is unknown; the daylight offset stands in for it
+                confidence = confidence.downgrade();
+            }
+
+            let coordinates = parse_coordinates(&line, opts.decimal_comma);
+            if opts.sanity_longitude {
+                if let Some((_latitude, longitude)) = coordinates {
+                    // the sun moves 15 degrees of longitude per hour
+                    let nominal_offset_hours = longitude / 15.0;
+                    let offset_hours = f64::from(offset.minutes()) / 60.0;
+                    if (offset_hours - nominal_offset_hours).abs() > 2.0 {
+                        eprintln!(
+                            "{}: offset UTC{} is implausible for longitude {:.2}; possible extraction error",
+                            icao, offset, longitude,
+                        );
+                    }
+                }
+            }
+
+            let candidates = if opts.timezone_db {
+                let region_hint_icao = if opts.use_region_hints { Some(icao.as_str()) } else { None };
+                let country_hint = time_zones.icao_countries.get(&icao).map(|c| c.as_str());
+                candidate_zones(offset, dst_offset, region_hint_icao, country_hint)
+            } else {
+                Vec::new()
+            };
+
+            let extra = extract_extra_fields(&line, &time_zones.extra_patterns);
+
+            let result = AirportResult {
+                icao,
+                iana: iana_timezone_opt,
+                utc_standard: offset,
+                utc_daylight: dst_offset,
+                confidence,
+                candidates,
+                latitude: coordinates.map(|(lat, _lon)| lat),
+                longitude: coordinates.map(|(_lat, lon)| lon),
+                page: page_index,
+                page_label: None,
+                section: None,
+                position: assembled.start,
+                line: line.to_string(),
+                extra,
+            };
+            if result.iana.is_some() {
+                stats.matched += 1;
+            } else {
+                stats.unmatched += 1;
+            }
+            page_results.push(result);
+        }
+    }
+
+    // in reading order (top to bottom, then left to right) whatever the order of the lines, e.g.
+    // with --use-struct-tree or --cell-gap; the pages themselves are passed on in order
+    page_results.sort_by_key(|result| result.position);
+    if let Some(report_dir) = opts.report_dir.as_ref() {
+        let report_path = report_dir.join(format!("{}.page{}.txt", document_name, page_index));
+        std::fs::write(&report_path, page_report(document_name, page_index, &page_results, &assembled_lines))
+            .unwrap_or_else(|e| panic!("failed to write {}: {}", report_path.display(), e));
+    }
+    for result in page_results {
+        callback(result);
+    }
+    stats
+}
+
+
+/// Compiles the report on a page written by --report-dir: the matched and unmatched airports,
+/// followed by all the assembled lines.
+fn page_report(document_name: &str, page_index: u32, results: &[AirportResult], lines: &[AssembledLine]) -> String {
+    let mut report = String::new();
+    writeln!(report, "{}, page index {}", document_name, page_index).unwrap();
+    for (heading, matched) in [("matched", true), ("unmatched", false)] {
+        writeln!(report).unwrap();
+        writeln!(report, "{}:", heading).unwrap();
+        for result in results.iter().filter(|r| r.iana.is_some() == matched) {
+            let mut entry = format!("    {} {} UTC{}", result.icao, result.iana.as_deref().unwrap_or("?"), result.utc_standard);
+            if let Some(dst) = result.utc_daylight {
+                write!(entry, " (DST UTC{})", dst).unwrap();
+            }
+            writeln!(report, "{} ({:?} confidence): {:?}", entry, result.confidence, result.line).unwrap();
+        }
+    }
+    writeln!(report).unwrap();
+    writeln!(report, "lines:").unwrap();
+    for line in lines {
+        writeln!(report, "    {}", line.display_text).unwrap();
+    }
+    report
+}
+
+
+/// Warns if the effective date in the document information (title, subject or keywords) differs
+/// from the one the time zone configuration was written for. This is only advisory, as the
+/// offsets may well be unchanged between editions.
+fn warn_on_edition_mismatch<OC, SC>(
+    pdf_file: &PdfFile<Vec<u8>, OC, SC>,
+    config_effective: &str,
+) where
+    OC: PdfCache<Result<AnySync, Arc<PdfError>>>,
+    SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let config_date = NaiveDate::parse_from_str(config_effective, "%Y-%m-%d")
+        .expect("effective date not validated");
+    let Some(info) = pdf_file.trailer.info_dict.as_ref() else { return };
+    let document_date = get_info_strings(info, pdf_file)
+        .iter()
+        .find_map(|s| parse_effective_date(s));
+    match document_date {
+        Some(date) if date != config_date => eprintln!(
+            "the time zone configuration is for the edition effective {} but the document is effective {}; offsets may have changed",
+            config_date, date,
+        ),
+        _ => {},
+    }
+}
+
+
+/// Assembles the lines of a page from its marked content, as grouped by the structure tree. Lines
+/// start at the position of their first piece of marked content.
+fn assemble_struct_tree_lines<OC, SC>(
+    pdf_file: &PdfFile<Vec<u8>, OC, SC>,
+    page_index: u32,
+    marked_text: &HashMap<i32, (Coords, String, DecodePath)>,
+    opts: &Opts,
+) -> Vec<AssembledLine> where
+    OC: PdfCache<Result<AnySync, Arc<PdfError>>>,
+    SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let page_references = get_page_references(pdf_file.get_root(), pdf_file);
+    let page_ref = page_references[usize::try_from(page_index).unwrap()].get_inner();
+    let root_ref = pdf_file.trailer.root.get_ref().get_inner();
+    let Some(struct_lines) = get_struct_tree_lines(root_ref, page_ref, pdf_file) else { return Vec::new() };
+
+    let mut lines = Vec::with_capacity(struct_lines.len());
+    for mcids in struct_lines {
+        let mut line_opt: Option<AssembledLine> = None;
+        for mcid in mcids {
+            let Some((coords, text, decode_path)) = marked_text.get(&mcid) else { continue };
+            let line = line_opt.get_or_insert_with(|| AssembledLine {
+                start: *coords,
+                text: String::new(),
+                display_text: String::new(),
+                decode_path: DecodePath::ToUnicode,
+                cells: Vec::new(),
+                fragment_offsets: Vec::new(),
+                band_cells: None,
+            });
+            if !line.text.is_empty() {
+                line.display_text.push_str(&opts.fragment_separator);
+            }
+            line.fragment_offsets.push((line.text.len(), coords.x));
+            line.text.push_str(text);
+            line.display_text.push_str(text);
+            line.decode_path = line.decode_path.min(*decode_path);
+        }
+        if let Some(line) = line_opt {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+
+/// Renders positioned text as an SVG document, with each fragment at its coordinates. Fragments
+/// decoded via the base encoding are drawn in blue, those interpreted raw in red.
+///
+/// If `normalized` is set, the coordinates are in 0..1 and scaled to the page size given by
+/// `width` and `height`; otherwise, the coordinates are used as they are and the image spans
+/// the fragments.
+fn page_text_svg(
+    coordinates_to_text: &BTreeMap<Coords, (String, DecodePath)>,
+    width: f32,
+    height: f32,
+    normalized: bool,
+) -> String {
+    let points: Vec<(f32, f32)> = coordinates_to_text.keys()
+        .map(|coords| {
+            let (x, y) = (f32::from(coords.x), f32::from(coords.y));
+            if normalized { (x * width, y * height) } else { (x, y) }
+        })
+        .collect();
+    let (left, top, right, bottom) = if normalized {
+        (0.0, 0.0, width, height)
+    } else {
+        points.iter().fold(
+            (0.0f32, 0.0f32, 1.0f32, 1.0f32),
+            |(l, t, r, b), (x, y)| (l.min(*x), t.min(*y), r.max(*x + 100.0), b.max(*y + 12.0)),
+        )
+    };
+
+    let mut svg = String::new();
+    writeln!(
+        svg, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}">"#,
+        left, top, right - left, bottom - top, right - left, bottom - top,
+    ).unwrap();
+    writeln!(svg, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="white"/>"#, left, top, right - left, bottom - top).unwrap();
+    for ((x, y), (text, decode_path)) in points.iter().zip(coordinates_to_text.values()) {
+        let fill = match decode_path {
+            DecodePath::ToUnicode => "black",
+            DecodePath::BaseEncoding => "blue",
+            DecodePath::Raw => "red",
+        };
+        let escaped = text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        writeln!(
+            svg, r#"<text x="{}" y="{}" font-size="8" font-family="sans-serif" fill="{}" xml:space="preserve">{}</text>"#,
+            x, y, fill, escaped,
+        ).unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+
+/// Decodes all the text on the page with the given index, ignoring its position, as a diagnostic of
+/// whether the page's fonts can be decoded at all. Each text object ends up on its own line.
+fn decode_page_text<OC, SC>(
+    pdf_file: &PdfFile<Vec<u8>, OC, SC>,
+    page_index: u32,
+    quiet: bool,
+) -> String where
+    OC: PdfCache<Result<AnySync, Arc<PdfError>>>,
+    SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let page = pdf_file.get_page(page_index)
+        .expect("failed to obtain page");
+    let Some(contents) = page.contents.as_ref() else { return String::new() };
+    let ops = contents.operations(pdf_file)
+        .expect("failed to obtain page ops");
+    let fonts = get_page_fonts(&page);
+
+    let mut ret = String::new();
+    let mut current_font = None;
+    for op in ops {
+        match op {
+            Op::EndText => ret.push('\n'),
+            Op::TextFont { name, .. } => {
+                current_font = fonts.get(name.as_str()).copied();
+            },
+            Op::TextDraw { text } => {
+                if let Ok(text_string) = text.to_string() {
+                    ret.push_str(&text_string);
+                }
+            },
+            Op::TextDrawAdjusted { array } => {
+                for adjustment in array {
+                    if let TextDrawAdjusted::Text(text) = adjustment {
+                        if let Some(decoded) = font_decode(current_font, text, pdf_file, quiet) {
+                            ret.push_str(&decoded.text);
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+    ret
+}
+
+
+/// Extracts the time zones of the airports in the given PDF file, invoking the callback for each
+/// airport as soon as it is found.
+fn extract_airport_timezones_cb<OC, SC>(
+    pdf_file: &PdfFile<Vec<u8>, OC, SC>,
+    document_name: &str,
+    opts: &Opts,
+    parsing: LineParsing,
+    time_zones: &TimeZoneConfig,
+    callback: &mut dyn FnMut(AirportResult),
+) -> ExtractionStats where
+    OC: PdfCache<Result<AnySync, Arc<PdfError>>>,
+    SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>>,
+    PdfFile<Vec<u8>, OC, SC>: Sync,
+{
+    if let Some(config_effective) = time_zones.effective.as_ref() {
+        warn_on_edition_mismatch(pdf_file, config_effective);
+    }
+
+    let page_labels = get_page_labels(pdf_file.trailer.root.get_ref().get_inner(), pdf_file);
+    let sections = if opts.split_by_section {
+        get_sections(pdf_file, opts)
+    } else {
+        Vec::new()
+    };
+    let callback = &mut |mut result: AirportResult| {
+        result.page_label = page_label(&page_labels, result.page);
+        result.section = section_of_page(&sections, result.page);
+        callback(result)
+    };
+
+    let page_indexes: Vec<u32> = if opts.per_page {
+        (0..pdf_file.num_pages()).collect()
+    } else {
+        find_directory_pages(pdf_file, opts).into_iter().collect()
+    };
+    let thread_count = opts.threads_per_pdf.max(1);
+    let mut stats = ExtractionStats::default();
+    if thread_count == 1 || page_indexes.len() < 2 {
+        for page_index in page_indexes {
+            stats += extract_airport_timezones_from_page(pdf_file, document_name, page_index, opts, parsing, time_zones, callback);
+        }
+        return stats;
+    }
+
+    // distribute the pages round-robin, then pass on the results in the original page order
+    let mut page_results: Vec<(usize, Vec<AirportResult>, ExtractionStats)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..thread_count)
+            .map(|thread_index| {
+                let thread_pages: Vec<(usize, u32)> = page_indexes.iter()
+                    .copied()
+                    .enumerate()
+                    .skip(thread_index)
+                    .step_by(thread_count)
+                    .collect();
+                scope.spawn(move || {
+                    thread_pages.into_iter()
+                        .map(|(order, page_index)| {
+                            let mut results = Vec::new();
+                            let page_stats = extract_airport_timezones_from_page(
+                                pdf_file, document_name, page_index, opts, parsing, time_zones,
+                                &mut |result| results.push(result),
+                            );
+                            (order, results, page_stats)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles.into_iter()
+            .flat_map(|handle| handle.join().expect("page extraction thread panicked"))
+            .collect()
+    });
+    page_results.sort_unstable_by_key(|(order, _, _)| *order);
+    for (_, results, page_stats) in page_results {
+        stats += page_stats;
+        for result in results {
+            callback(result);
+        }
+    }
+    stats
+}
+
+
+/// Returns the first page index and title of each bookmark at any nesting level, in document
+/// order, for --split-by-section.
+fn get_sections<OC, SC>(pdf_file: &PdfFile<Vec<u8>, OC, SC>, opts: &Opts) -> Vec<(u32, String)> where
+    OC: PdfCache<Result<AnySync, Arc<PdfError>>>,
+    SC: PdfCache<Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let destination_pages = get_destination_pages(pdf_file.get_root(), pdf_file);
+    let page_references = get_page_references(pdf_file.get_root(), pdf_file);
+    get_all_bookmarks(pdf_file.get_root(), pdf_file, opts.quiet)
+        .into_iter()
+        .filter_map(|bookmark| {
+            let page_index = bookmark_destination_to_page_index(
+                &bookmark.destination,
+                &destination_pages,
+                &page_references,
+            )?;
+            Some((page_index, bookmark.title))
+        })
+        .collect()
+}
+
+
+/// Returns the title of the most specific section covering the page with the given index: the
+/// section starting last at or before it, or, if several start on the same page, the one coming
+/// last in document order (i.e. the most deeply nested).
+fn section_of_page(sections: &[(u32, String)], page_index: u32) -> Option<String> {
+    sections.iter()
+        .enumerate()
+        .filter(|(_order, (start_page, _title))| *start_page <= page_index)
+        .max_by_key(|(order, (start_page, _title))| (*start_page, *order))
+        .map(|(_order, (_start_page, title))| title.clone())
+}
+
+
+/// Whether opening a document failed because it is encrypted and the password is wrong or missing.
+fn is_invalid_password(error: &PdfError) -> bool {
+    match error {
+        PdfError::InvalidPassword => true,
+        PdfError::Try { source, .. } => is_invalid_password(source),
+        _ => false,
+    }
+}
+
+fn panic_on_invalid_password(pdf_path: &Path, opts: &Opts) -> ! {
+    if opts.password.is_some() {
+        panic!("failed to open PDF file {}: the document is encrypted and the password is wrong", pdf_path.display());
+    } else {
+        panic!("failed to open PDF file {}: the document is encrypted; pass its password using --password", pdf_path.display());
+    }
+}
+
+
+/// Opens the PDF file at the given path and extracts the time zones of its airports.
+///
+/// Both classic cross-reference tables and cross-reference streams (as used by linearized and
+/// incrementally updated documents) are read by the `pdf` crate. If the document cannot be opened
+/// with the cached reader, the uncached reader is tried; if that fails too, the document is parsed
+/// once more while tolerating cross-reference errors, which lets the `pdf` crate reconstruct a
+/// damaged cross-reference table.
+fn extract_airport_timezones_from_path(
+    pdf_path: &Path,
+    opts: &Opts,
+    parsing: LineParsing,
+    time_zones: &TimeZoneConfig,
+    callback: &mut dyn FnMut(AirportResult),
+) -> ExtractionStats {
+    let document_name = pdf_path.file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let password = opts.password.as_deref().unwrap_or("").as_bytes();
+    let cached_error = match PdfFileOptions::cached().password(password).open(pdf_path) {
+        Ok(pdf_file) => {
+            return extract_airport_timezones_cb(&pdf_file, &document_name, opts, parsing, time_zones, callback);
+        },
+        Err(e) if is_invalid_password(&e) => panic_on_invalid_password(pdf_path, opts),
+        Err(e) => e,
+    };
+
+    // some documents only open with one of the two readers
+    eprintln!("failed to open {} with caching ({}); retrying without", pdf_path.display(), cached_error);
+    let uncached_error = match PdfFileOptions::uncached().password(password).open(pdf_path) {
+        Ok(pdf_file) => {
+            eprintln!("opened {} without caching", pdf_path.display());
+            return extract_airport_timezones_cb(&pdf_file, &document_name, opts, parsing, time_zones, callback);
+        },
+        Err(e) => e,
+    };
+
+    eprintln!("failed to open {} without caching ({}); retrying with tolerant parsing", pdf_path.display(), uncached_error);
+    let pdf_file = PdfFileOptions::cached()
+        .password(password)
+        .parse_options(ParseOptions::tolerant())
+        .open(pdf_path)
+        .unwrap_or_else(|e| panic!("failed to open PDF file {}: {}", pdf_path.display(), e));
+    eprintln!("opened {} with tolerant parsing", pdf_path.display());
+    extract_airport_timezones_cb(&pdf_file, &document_name, opts, parsing, time_zones, callback)
+}
+
+
+/// Opens the PDF file at the given path and extracts the time zones of its airports, returning the
+/// matched and unmatched airports separately instead of passing them to a callback.
+fn extract_airport_timezones(pdf_path: &Path, opts: &Opts, parsing: LineParsing, time_zones: &TimeZoneConfig) -> ExtractionOutput {
+    let mut output = ExtractionOutput::default();
+    extract_airport_timezones_from_path(pdf_path, opts, parsing, time_zones, &mut |result| output.push(result));
+    output
+}
+
+
+/// Extracts the time zones of the airports in the document at the given path and returns them
+/// along with statistics on the extraction.
+///
+/// With `--timeout`, the document is processed on a separate thread; if it takes longer than
+/// allowed, a warning is output and `None` is returned. The thread cannot be stopped, so it
+/// continues running (and holding on to its memory) in the background until the process exits.
+fn extract_document_results(
+    pdf_path: &Path,
+    opts: &Arc<Opts>,
+    parsing: LineParsing,
+    time_zones: &TimeZoneConfig,
+) -> Option<(Vec<AirportResult>, ExtractionStats)> {
+    let Some(timeout) = opts.timeout else {
+        let document_time_zones = document_time_zones(pdf_path, opts, time_zones);
+        let mut document_results = Vec::new();
+        let stats = extract_airport_timezones_from_path(
+            pdf_path, opts, parsing, &document_time_zones,
+            &mut |result| document_results.push(result),
+        );
+        return Some((document_results, stats));
+    };
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let thread_path = pdf_path.to_owned();
+    let thread_opts = Arc::clone(opts);
+    let thread_time_zones = document_time_zones(pdf_path, opts, time_zones).into_owned();
+    std::thread::spawn(move || {
+        let mut document_results = Vec::new();
+        let stats = extract_airport_timezones_from_path(
+            &thread_path, &thread_opts, parsing, &thread_time_zones,
+            &mut |result| document_results.push(result),
+        );
+        // the receiver is gone if we took too long
+        let _ = sender.send((document_results, stats));
+    });
+    match receiver.recv_timeout(Duration::from_secs_f64(timeout)) {
+        Ok(document_output) => Some(document_output),
+        Err(RecvTimeoutError::Timeout) => {
+            eprintln!("abandoning {}, which took longer than {} seconds", pdf_path.display(), timeout);
+            None
+        },
+        Err(RecvTimeoutError::Disconnected) => panic!("document extraction thread for {} panicked", pdf_path.display()),
+    }
+}
+
+
+/// Returns the time zone configuration for the given document: the global configuration, with
+/// the definitions from `foo.toml` next to `foo.pdf` merged in if it exists.
+fn document_time_zones<'a>(pdf_path: &Path, opts: &Opts, time_zones: &'a TimeZoneConfig) -> Cow<'a, TimeZoneConfig> {
+    let sidecar_path = pdf_path.with_extension("toml");
+    let use_config = !(opts.timezone_db || opts.no_config);
+    if use_config && sidecar_path.is_file() {
+        eprintln!("applying time zone overrides from {} to {}", sidecar_path.display(), pdf_path.display());
+        let mut document_time_zones = time_zones.clone();
+        merge_time_zone_file(&mut document_time_zones, &sidecar_path);
+        Cow::Owned(document_time_zones)
+    } else {
+        Cow::Borrowed(time_zones)
+    }
+}
+
+
+/// Groups the ICAO codes of the results by time zone. Airports without a time zone are grouped
+/// under "?".
+fn group_by_zone(results: &[AirportResult]) -> BTreeMap<&str, Vec<&str>> {
+    let mut zone_to_icaos: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for result in results {
+        let zone = result.iana.as_deref().unwrap_or("?");
+        zone_to_icaos.entry(zone)
+            .or_default()
+            .push(&result.icao);
+    }
+    zone_to_icaos
+}
+
+
+/// Converts the results into a GeoJSON FeatureCollection of points. Airports without coordinates
+/// are omitted unless `include_unlocated` is set, in which case they have a null geometry.
+fn to_geojson(results: &[AirportResult], include_unlocated: bool) -> serde_json::Value {
+    let mut features = Vec::with_capacity(results.len());
+    for result in results {
+        let geometry = match (result.latitude, result.longitude) {
+            (Some(latitude), Some(longitude)) => serde_json::json!({
+                "type": "Point",
+                // GeoJSON positions are longitude first
+                "coordinates": [longitude, latitude],
+            }),
+            _ => {
+                if !include_unlocated {
+                    continue;
+                }
+                serde_json::Value::Null
+            },
+        };
+        features.push(serde_json::json!({
+            "type": "Feature",
+            "geometry": geometry,
+            "properties": {
+                "icao": result.icao,
+                "iana": result.iana,
+            },
+        }));
+    }
+    serde_json::json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+
+/// The time zone of an airport as recorded by --only-changed.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+struct StoredResolution {
+    pub icao: String,
+    pub iana: Option<String>,
+    /// When the airport was first found with this time zone (RFC 3339).
+    pub first_seen: String,
+    /// When the airport was last found with this time zone (RFC 3339).
+    pub last_seen: String,
+}
+
+
+/// Reads the --only-changed store; a missing file is an empty store.
+fn load_resolution_store(path: &Path) -> BTreeMap<String, StoredResolution> {
+    let store_string = match std::fs::read_to_string(path) {
+        Ok(ss) => ss,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return BTreeMap::new(),
+        Err(e) => panic!("failed to read store {}: {}", path.display(), e),
+    };
+    let resolutions: Vec<StoredResolution> = serde_json::from_str(&store_string)
+        .unwrap_or_else(|e| panic!("failed to parse store {}: {}", path.display(), e));
+    resolutions.into_iter()
+        .map(|resolution| (resolution.icao.clone(), resolution))
+        .collect()
+}
+
+
+fn save_resolution_store(path: &Path, store: &BTreeMap<String, StoredResolution>) {
+    let resolutions: Vec<&StoredResolution> = store.values().collect();
+    let json = serde_json::to_string_pretty(&resolutions)
+        .expect("failed to serialize store");
+    std::fs::write(path, json)
+        .unwrap_or_else(|e| panic!("failed to write store {}: {}", path.display(), e));
+}
+
+
+/// Records the time zone of the airport in the --only-changed store and returns whether it differs
+/// from the previously recorded one.
+fn record_resolution(store: &mut BTreeMap<String, StoredResolution>, result: &AirportResult, now: &str) -> bool {
+    match store.get_mut(&result.icao) {
+        Some(stored) if stored.iana == result.iana => {
+            stored.last_seen = now.to_owned();
+            false
+        },
+        _ => {
+            store.insert(result.icao.clone(), StoredResolution {
+                icao: result.icao.clone(),
+                iana: result.iana.clone(),
+                first_seen: now.to_owned(),
+                last_seen: now.to_owned(),
+            });
+            true
+        },
+    }
+}
+
+
+/// An airport in the output of a previous run, as far as --baseline compares it; the other fields
+/// of a result are ignored, so that the output of older versions can still be read.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+struct BaselineAirport {
+    pub icao: String,
+    #[serde(default)]
+    pub iana: Option<String>,
+}
+
+
+/// Reads the --baseline file, either a JSON array (--format json) or one JSON object per line
+/// (--format jsonl).
+fn load_baseline(path: &Path) -> Vec<BaselineAirport> {
+    let baseline_string = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("failed to read baseline {}: {}", path.display(), e));
+    if baseline_string.trim_start().starts_with('[') {
+        return serde_json::from_str(&baseline_string)
+            .unwrap_or_else(|e| panic!("failed to parse baseline {}: {}", path.display(), e));
+    }
+    baseline_string.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_index, line)| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("failed to parse line {} of baseline {}: {}", line_index + 1, path.display(), e))
+        })
+        .collect()
+}
+
+
+fn report_changes(baseline: &[BaselineAirport], current: &[AirportResult]) {
+    let baseline_zones: BTreeMap<&str, Option<&str>> = baseline.iter()
+        .map(|r| (r.icao.as_str(), r.iana.as_deref()))
+        .collect();
+    let current_zones: BTreeMap<&str, Option<&str>> = current.iter()
+        .map(|r| (r.icao.as_str(), r.iana.as_deref()))
+        .collect();
+
+    for (icao, current_zone) in &current_zones {
+        match baseline_zones.get(icao) {
+            None => {
+                eprintln!("added {}: {}", icao, current_zone.unwrap_or("?"));
+            },
+            Some(baseline_zone) if baseline_zone != current_zone => {
+                eprintln!(
+                    "changed {}: {} -> {}", icao,
+                    baseline_zone.unwrap_or("?"), current_zone.unwrap_or("?"),
+                );
+            },
+            Some(_) => {},
+        }
+    }
+    for (icao, baseline_zone) in &baseline_zones {
+        if !current_zones.contains_key(icao) {
+            eprintln!("removed {}: {}", icao, baseline_zone.unwrap_or("?"));
+        }
+    }
+}
+
+
+fn main() {
+    // shared with the worker threads of --timeout, which may outlive their document
+    let opts = Arc::new(Opts::parse());
+    // the patterns are chosen once for the whole run
+    let parsing = LineParsing::from_opts(&opts);
+
+    let mut time_zones = if opts.timezone_db || opts.no_config {
+        TimeZoneConfig::default()
+    } else {
+        load_time_zones(&opts.time_zones)
+    };
+    if let Some(icao_countries_path) = opts.icao_countries.as_ref() {
+        time_zones.icao_countries = load_icao_countries(icao_countries_path);
+    }
+    if opts.self_test {
+        let pdf_file = PdfFileOptions::cached()
+            .load(SELF_TEST_PDF.to_vec())
+            .expect("failed to load self-test document");
+        let decoded = decode_page_text(&pdf_file, 0, opts.quiet);
+        if decoded != SELF_TEST_EXPECTED {
+            eprintln!("self-test failed");
+            eprintln!("expected: {:?}", SELF_TEST_EXPECTED);
+            eprintln!("decoded:  {:?}", decoded);
+            std::process::exit(1);
+        }
+        eprintln!("self-test passed");
+        return;
+    }
+    if let Some(page_index) = opts.decode_only {
+        for pdf_path in &opts.pdf_paths {
+            let pdf_file = PdfFileOptions::cached()
+                .password(opts.password.as_deref().unwrap_or("").as_bytes())
+                .open(pdf_path)
+                .unwrap_or_else(|e| {
+                    if is_invalid_password(&e) {
+                        panic_on_invalid_password(pdf_path, &opts);
+                    }
+                    panic!("failed to open PDF file {}: {}", pdf_path.display(), e)
+                });
+            println!("{}", decode_page_text(&pdf_file, page_index, opts.quiet));
+        }
+        return;
+    }
+    if opts.validate_roundtrip {
+        let mismatches = validate_roundtrip(&time_zones);
+        if mismatches > 0 {
+            eprintln!("{} time zone definitions or patterns do not survive a round trip", mismatches);
+            std::process::exit(1);
+        }
+        eprintln!("all time zone definitions and patterns survive a round trip");
+        return;
+    }
+    if opts.list_unmatched {
+        for pdf_path in &opts.pdf_paths {
+            let document_time_zones = document_time_zones(pdf_path, &opts, &time_zones);
+            let output = extract_airport_timezones(pdf_path, &opts, parsing, &document_time_zones);
+            for unmatched in &output.unmatched {
+                let mut offsets = format!("UTC{}", unmatched.utc_standard);
+                if let Some(dst) = unmatched.utc_daylight {
+                    write!(offsets, " (DST UTC{})", dst).unwrap();
+                }
+                println!("{} {}: {:?}", unmatched.icao, offsets, unmatched.line);
+            }
+            eprintln!(
+                "{}: {} matched, {} unmatched",
+                pdf_path.display(), output.matched.len(), output.unmatched.len(),
+            );
+        }
+        return;
+    }
+    let baseline: Option<Vec<BaselineAirport>> = opts.baseline.as_ref()
+        .map(|path| load_baseline(path));
+
+    let mut resolution_store = opts.only_changed.as_ref()
+        .map(|store_path| load_resolution_store(store_path));
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    let mut results = Vec::new();
+    let mut stats = ExtractionStats::default();
+    let mut sampled = 0;
+    let mut current_section: Option<String> = None;
+    let mut posix_tz_strings: HashMap<String, String> = HashMap::new();
+    let mut output_result = |results: &mut Vec<AirportResult>, mut result: AirportResult| {
+        if let Some(sample) = opts.sample {
+            // show what was parsed, unfiltered
+            if sampled >= sample {
+                std::process::exit(0);
+            }
+            println!("{:?}", result.line);
+            let mut parsed = format!("  {} UTC{}", result.icao, result.utc_standard);
+            if let Some(dst) = result.utc_daylight {
+                write!(parsed, " (DST UTC{})", dst).unwrap();
+            }
+            write!(
+                parsed, " => {} ({:?} confidence)",
+                result.iana.as_deref().unwrap_or("?"), result.confidence,
+            ).unwrap();
+            match result.page_label.as_deref() {
+                Some(label) => write!(parsed, " on page index {} (labeled {:?})", result.page, label),
+                None => write!(parsed, " on page index {}", result.page),
+            }.unwrap();
+            println!("{}", parsed);
+            sampled += 1;
+            if sampled >= sample {
+                std::process::exit(0);
+            }
+            return;
+        }
+
+        if result.confidence < opts.min_confidence {
+            return;
+        }
+        let country_matches = opts.country_prefix.is_empty()
+            || opts.country_prefix.iter().any(|prefix| result.icao.starts_with(&prefix.to_uppercase()));
+        if !country_matches {
+            return;
+        }
+        if result.iana.is_none() {
+            result.iana = match opts.emit_unmatched_as {
+                Some(UnmatchedFallback::EtcGmt) => Some(etc_gmt_zone(result.utc_standard)),
+                None => None,
+            };
+        }
+        if let Some(store) = resolution_store.as_mut() {
+            if !record_resolution(store, &result, &now) {
+                return;
+            }
+        }
+        match opts.format {
+            OutputFormat::Text if opts.group_by_zone => {},
+            OutputFormat::Text => {
+                let iana_timezone = result.iana.as_deref().unwrap_or("?");
+                let mut line = format!("{} {}", result.icao, iana_timezone);
+                if opts.show_offsets {
+                    match result.utc_daylight {
+                        Some(dst) => write!(line, " {}/{}", result.utc_standard, dst),
+                        None => write!(line, " {}", result.utc_standard),
+                    }.unwrap();
+                }
+                if !result.candidates.is_empty() {
+                    write!(line, " ({})", result.candidates.join(", ")).unwrap();
+                }
+                if opts.split_by_section {
+                    if current_section != result.section {
+                        println!("{}:", result.section.as_deref().unwrap_or("(no section)"));
+                        current_section = result.section.clone();
+                    }
+                    println!("    {}", line);
+                } else {
+                    println!("{}", line);
+                }
+            },
+            OutputFormat::Posix => {
+                let tz_string = match result.iana.as_deref() {
+                    Some(iana) => posix_tz_strings.entry(iana.to_owned())
+                        .or_insert_with(|| posix_tz_string(iana).unwrap_or_else(|| iana.to_owned()))
+                        .clone(),
+                    None => "?".to_owned(),
+                };
+                println!("{} {}", result.icao, tz_string);
+            },
+            OutputFormat::Jsonl => {
+                let json = serde_json::to_string(&result)
+                    .expect("failed to serialize result");
+                println!("{}", json);
+            },
+            OutputFormat::Json|OutputFormat::Geojson => {},
+        }
+        let collect_results = matches!(opts.format, OutputFormat::Json|OutputFormat::Geojson)
+            || (opts.format == OutputFormat::Text && opts.group_by_zone)
+            || baseline.is_some();
+        if collect_results {
+            // must be collected to output a single document or compare against the baseline
+            results.push(result);
+        }
+    };
+    let completed_paths: HashSet<PathBuf> = match opts.resume.as_ref() {
+        Some(resume_path) => match std::fs::read_to_string(resume_path) {
+            Ok(completed) => completed.lines()
+                .filter(|l| !l.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => panic!("failed to read resume file {}: {}", resume_path.display(), e),
+        },
+        None => HashSet::new(),
+    };
+    let mut resume_file = opts.resume.as_ref().map(|resume_path| {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(resume_path)
+            .unwrap_or_else(|e| panic!("failed to open resume file {}: {}", resume_path.display(), e))
+    });
+
+    let pending_paths: Vec<&PathBuf> = opts.pdf_paths.iter()
+        .filter(|pdf_path| {
+            let completed = completed_paths.contains(*pdf_path);
+            if completed {
+                eprintln!("skipping {}, which has already been processed", pdf_path.display());
+            }
+            !completed
+        })
+        .collect();
+    for batch in pending_paths.chunks(opts.pdf_parallelism.max(1)) {
+        // results are output in reading order: by document, then page, then top to bottom, then
+        // left to right (the extraction passes on the results of each document in that order)
+        let completed_batch_paths: Vec<&PathBuf> = match batch {
+            [pdf_path] if opts.timeout.is_none() => {
+                // a single document: output the results as they are found
+                let document_time_zones = document_time_zones(pdf_path, &opts, &time_zones);
+                stats += extract_airport_timezones_from_path(
+                    pdf_path, &opts, parsing, &document_time_zones,
+                    &mut |result| output_result(&mut results, result),
+                );
+                vec![*pdf_path]
+            },
+            _ => {
+                let batch_results: Vec<Option<(Vec<AirportResult>, ExtractionStats)>> = std::thread::scope(|scope| {
+                    let handles: Vec<_> = batch.iter()
+                        .map(|pdf_path| scope.spawn(|| extract_document_results(pdf_path, &opts, parsing, &time_zones)))
+                        .collect();
+                    handles.into_iter()
+                        .map(|handle| handle.join().expect("document extraction thread panicked"))
+                        .collect()
+                });
+                let mut completed = Vec::with_capacity(batch.len());
+                for (pdf_path, document_results) in batch.iter().zip(batch_results) {
+                    let Some((document_results, document_stats)) = document_results else { continue };
+                    stats += document_stats;
+                    for result in document_results {
+                        output_result(&mut results, result);
+                    }
+                    completed.push(*pdf_path);
+                }
+                completed
+            },
+        };
+
+        if let Some(resume_file) = resume_file.as_mut() {
+            for pdf_path in completed_batch_paths {
+                writeln!(resume_file, "{}", pdf_path.display())
+                    .and_then(|()| resume_file.flush())
+                    .expect("failed to write to resume file");
+            }
+        }
+    }
+    if opts.sample.is_some() {
+        // fewer airports than requested were found; the samples are all that is output
+        return;
+    }
+
+    match opts.format {
+        OutputFormat::Text if opts.group_by_zone => {
+            for (zone, icaos) in group_by_zone(&results) {
+                println!("{}:", zone);
+                for icao in icaos {
+                    println!("    {}", icao);
+                }
+            }
+        },
+        OutputFormat::Text|OutputFormat::Jsonl|OutputFormat::Posix => {},
+        OutputFormat::Json if opts.group_by_zone => {
+            let json = serde_json::to_string_pretty(&group_by_zone(&results))
+                .expect("failed to serialize results");
+            println!("{}", json);
+        },
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&results)
+                .expect("failed to serialize results");
+            println!("{}", json);
+        },
+        OutputFormat::Geojson => {
+            let feature_collection = to_geojson(&results, opts.include_unlocated);
+            let json = serde_json::to_string_pretty(&feature_collection)
+                .expect("failed to serialize results");
+            println!("{}", json);
+        },
+    }
+
+    if let Some(baseline) = baseline.as_ref() {
+        report_changes(baseline, &results);
+    }
+    if opts.stats {
+        eprintln!("{}", stats);
+    }
+    if let (Some(store_path), Some(store)) = (opts.only_changed.as_ref(), resolution_store.as_ref()) {
+        save_resolution_store(store_path, store);
+    }
 }
@@ -1,18 +1,20 @@
 mod encoding;
 
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::hash::{Hash, Hasher};
 
 use pdf::encoding::BaseEncoding;
 use pdf::font::Font;
 use pdf::object::{
-    Action, Catalog, MaybeNamedDest, MaybeRef, Page, PagesNode, PageTree, Ref, Resolve,
+    Action, Catalog, MaybeNamedDest, MaybeRef, OutlineItem, Page, PagesNode, PageTree, PlainRef, Ref, Resolve,
 };
-use pdf::primitive::PdfString;
+use pdf::primitive::{Dictionary, PdfString, Primitive};
 
 use crate::pdf_reading::encoding::{
-    MAC_ROMAN_ENCODING, NAME_TO_CHARACTER, STANDARD_ENCODING, SYMBOL_ENCODING, WIN_ANSI_ENCODING,
+    MAC_ROMAN_ENCODING, NAME_TO_CHARACTER, PDF_DOC_ENCODING, STANDARD_ENCODING, SYMBOL_ENCODING,
+    WIN_ANSI_ENCODING,
 };
 
 
@@ -20,6 +22,23 @@ use crate::pdf_reading::encoding::{
 pub(crate) enum BookmarkDestination {
     Named(String),
     Page(Ref<Page>),
+    /// A page given by its (0-based) index, e.g. by a named FirstPage action.
+    PageIndex(u32),
+    /// The last page of the document, i.e. a named LastPage action.
+    LastPage,
+}
+impl fmt::Display for BookmarkDestination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Named(name) => write!(f, "named destination {:?}", name),
+            Self::Page(page_ref) => {
+                let plain_ref = page_ref.get_inner();
+                write!(f, "page object {} {} R", plain_ref.id, plain_ref.gen)
+            },
+            Self::PageIndex(index) => write!(f, "page index {}", index),
+            Self::LastPage => write!(f, "last page"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -30,6 +49,18 @@ pub(crate) struct Bookmark {
 }
 
 
+/// How a piece of text was decoded, from least to most trustworthy.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum DecodePath {
+    /// The string was interpreted without consulting the font.
+    Raw,
+    /// The font's base encoding (plus differences) was used.
+    BaseEncoding,
+    /// The font's ToUnicode map was used.
+    ToUnicode,
+}
+
+
 #[derive(Clone, Copy, Debug, Default)]
 pub(crate) struct NoNonsenseF32(f32);
 impl NoNonsenseF32 {
@@ -52,23 +83,34 @@ impl From<NoNonsenseF32> for f32 {
 }
 impl PartialEq for NoNonsenseF32 {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        self.cmp(other) == std::cmp::Ordering::Equal
     }
 }
 impl Eq for NoNonsenseF32 {}
 impl PartialOrd for NoNonsenseF32 {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.0.partial_cmp(&other.0).unwrap())
+        Some(self.cmp(other))
     }
 }
 impl Ord for NoNonsenseF32 {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        // NaN cannot be constructed via TryFrom, but should it slip in anyway, order it after
+        // every number (and equal to itself) instead of panicking
+        self.0.partial_cmp(&other.0)
+            .unwrap_or_else(|| self.0.is_nan().cmp(&other.0.is_nan()))
     }
 }
 impl Hash for NoNonsenseF32 {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.to_bits().hash(state);
+        // consistent with Eq: 0.0 and -0.0 are equal, as are all NaNs
+        let canonical = if self.0.is_nan() {
+            f32::NAN
+        } else if self.0 == 0.0 {
+            0.0
+        } else {
+            self.0
+        };
+        canonical.to_bits().hash(state);
     }
 }
 
@@ -78,6 +120,23 @@ pub(crate) struct Coords {
     pub y: NoNonsenseF32,
     pub x: NoNonsenseF32,
 }
+impl Coords {
+    /// The Euclidean distance between the two points.
+    #[allow(dead_code)]
+    pub fn distance(&self, other: &Coords) -> f32 {
+        self.horizontal_distance(other).hypot(self.vertical_distance(other))
+    }
+
+    /// The distance between the two points along the X axis.
+    pub fn horizontal_distance(&self, other: &Coords) -> f32 {
+        (f32::from(self.x) - f32::from(other.x)).abs()
+    }
+
+    /// The distance between the two points along the Y axis.
+    pub fn vertical_distance(&self, other: &Coords) -> f32 {
+        (f32::from(self.y) - f32::from(other.y)).abs()
+    }
+}
 
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -116,6 +175,117 @@ impl Matrix2D {
             y: ny.try_into().unwrap(),
         }
     }
+
+    /// Returns a matrix translating by the given offsets.
+    pub fn translation(x: f32, y: f32) -> Matrix2D {
+        Self {
+            c0: x.try_into().expect("translation is not finite"),
+            c1: y.try_into().expect("translation is not finite"),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the product of this matrix and the other matrix (`self × other`).
+    pub fn multiply(&self, other: &Matrix2D) -> Matrix2D {
+        let s = self.rows();
+        let o = other.rows();
+        let mut product = [[0.0f32; 3]; 3];
+        for (r, product_row) in product.iter_mut().enumerate() {
+            for (c, product_cell) in product_row.iter_mut().enumerate() {
+                *product_cell = (0..3).map(|i| s[r][i] * o[i][c]).sum();
+            }
+        }
+        Self::try_from_rows(product).expect("matrix product is not finite")
+    }
+
+    /// Returns the inverse of this matrix, or `None` if the matrix is singular or its inverse is not
+    /// finite.
+    #[allow(dead_code)]
+    pub fn inverse(&self) -> Option<Matrix2D> {
+        let [[a0, b0, c0], [a1, b1, c1], [a2, b2, c2]] = self.rows();
+
+        // cofactors
+        let ca0 = b1 * c2 - c1 * b2;
+        let cb0 = -(a1 * c2 - c1 * a2);
+        let cc0 = a1 * b2 - b1 * a2;
+
+        // no absolute threshold: the determinant of a matrix scaling by 0.0001 (which PDFs do use)
+        // is 1e-8, yet the matrix is perfectly invertible; a determinant too small to divide by
+        // yields a non-finite inverse, which is rejected below
+        let determinant = a0 * ca0 + b0 * cb0 + c0 * cc0;
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+
+        let ca1 = -(b0 * c2 - c0 * b2);
+        let cb1 = a0 * c2 - c0 * a2;
+        let cc1 = -(a0 * b2 - b0 * a2);
+        let ca2 = b0 * c1 - c0 * b1;
+        let cb2 = -(a0 * c1 - c0 * a1);
+        let cc2 = a0 * b1 - b0 * a1;
+
+        // inverse = transposed cofactor matrix / determinant
+        Self::try_from_rows([
+            [ca0 / determinant, ca1 / determinant, ca2 / determinant],
+            [cb0 / determinant, cb1 / determinant, cb2 / determinant],
+            [cc0 / determinant, cc1 / determinant, cc2 / determinant],
+        ])
+    }
+
+    fn rows(&self) -> [[f32; 3]; 3] {
+        [
+            [f32::from(self.a0), f32::from(self.b0), f32::from(self.c0)],
+            [f32::from(self.a1), f32::from(self.b1), f32::from(self.c1)],
+            [f32::from(self.a2), f32::from(self.b2), f32::from(self.c2)],
+        ]
+    }
+
+    fn try_from_rows(rows: [[f32; 3]; 3]) -> Option<Matrix2D> {
+        Some(Self {
+            a0: rows[0][0].try_into().ok()?,
+            b0: rows[0][1].try_into().ok()?,
+            c0: rows[0][2].try_into().ok()?,
+            a1: rows[1][0].try_into().ok()?,
+            b1: rows[1][1].try_into().ok()?,
+            c1: rows[1][2].try_into().ok()?,
+            a2: rows[2][0].try_into().ok()?,
+            b2: rows[2][1].try_into().ok()?,
+            c2: rows[2][2].try_into().ok()?,
+        })
+    }
+
+    /// The size at which text set in a font of the given size is drawn when transformed by this
+    /// matrix, i.e. the length of the transformed vertical unit vector multiplied by the font size.
+    pub fn effective_font_size(&self, font_size: f32) -> f32 {
+        let b0 = f32::from(self.b0);
+        let b1 = f32::from(self.b1);
+        font_size * (b0 * b0 + b1 * b1).sqrt()
+    }
+
+    /// Whether this matrix rotates text away from the horizontal baseline, e.g. for vertical column
+    /// headers.
+    ///
+    /// Only the direction of the baseline (the transformed horizontal unit vector) counts, so
+    /// shearing, as used for synthetic italics and oblique fonts, is not rotation.
+    pub fn is_rotated(&self) -> bool {
+        const EPSILON: f32 = 0.001;
+        let (a0, a1) = (f32::from(self.a0), f32::from(self.a1));
+        a0 <= 0.0 || a1.abs() > EPSILON * a0.abs()
+    }
+
+    /// Whether this matrix rotates text away from the horizontal baseline of a page displayed
+    /// rotated by the given number of degrees (0, 90, 180 or 270).
+    pub fn is_rotated_on_page(&self, page_rotation: i32) -> bool {
+        const EPSILON: f32 = 0.001;
+        let (a0, a1) = (f32::from(self.a0), f32::from(self.a1));
+        match page_rotation {
+            // the page's horizontal runs along the vertical axis of the unrotated page, upwards
+            // for a page displayed rotated by 90 degrees and downwards for 270 degrees
+            90 => a1 <= 0.0 || a0.abs() > EPSILON * a1.abs(),
+            270 => a1 >= 0.0 || a0.abs() > EPSILON * a1.abs(),
+            _ => self.is_rotated(),
+        }
+    }
 }
 impl Default for Matrix2D {
     fn default() -> Self {
@@ -136,71 +306,179 @@ impl Default for Matrix2D {
 }
 
 
-pub(crate) fn get_top_level_bookmarks<R: Resolve>(pdf_root: &Catalog, resolve: &R) -> Vec<Bookmark> {
+/// Returns the title, subject and keywords from the document information dictionary, where present.
+pub(crate) fn get_info_strings<R: Resolve>(info: &Dictionary, resolve: &R) -> Vec<String> {
+    let mut ret = Vec::new();
+    for key in ["Title", "Subject", "Keywords"] {
+        let Some(value) = info.get(key) else { continue };
+        let Ok(resolved) = value.clone().resolve(resolve) else { continue };
+        if let Primitive::String(string) = resolved {
+            ret.push(decode_text_string(&string));
+        }
+    }
+    ret
+}
+
+
+/// Decodes a text string such as a bookmark title.
+///
+/// Strings starting with a byte order mark are decoded as UTF-16BE, UTF-16LE (not allowed by the
+/// specification but found in the wild) or UTF-8; all others are decoded using PDFDocEncoding.
+/// Undecodable sequences are replaced by U+FFFD.
+pub(crate) fn decode_text_string(string: &PdfString) -> String {
+    let bytes = string.as_bytes();
+    if let Some(utf16be) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16be.chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+            .collect();
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    } else if let Some(utf16le) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        let units: Vec<u16> = utf16le.chunks(2)
+            .map(|c| u16::from_le_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+            .collect();
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    } else if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8_lossy(utf8).into_owned()
+    } else {
+        bytes.iter()
+            .map(|b| match PDF_DOC_ENCODING.get(b) {
+                Some(c) => *c,
+                None if b.is_ascii() => char::from(*b),
+                None => char::REPLACEMENT_CHARACTER,
+            })
+            .collect()
+    }
+}
+
+
+pub(crate) fn get_top_level_bookmarks<R: Resolve>(pdf_root: &Catalog, resolve: &R, quiet: bool) -> Vec<Bookmark> {
     let Some(outlines) = pdf_root.outlines.as_ref() else { return Vec::with_capacity(0) };
     let Some(first_outline_ref) = outlines.first else { return Vec::with_capacity(0) };
-    let mut current_outline = resolve.get(first_outline_ref)
-        .expect("failed to resolve first outline");
     let mut ret = Vec::new();
-    loop {
+    // the outlines still to be visited, the next one on top
+    let mut pending_refs = vec![first_outline_ref];
+    while let Some(outline_ref) = pending_refs.pop() {
+        let current_outline = resolve.get(outline_ref)
+            .expect("failed to resolve outline");
+        if let Some(next_outline_ref) = current_outline.next {
+            pending_refs.push(next_outline_ref);
+        }
+
         let title_opt = current_outline.title
             .as_ref()
-            .map(|t| t.to_string().expect("failed to decode string"));
+            .map(decode_text_string);
+        if title_opt.is_none() {
+            if let Some(first_child_ref) = current_outline.first {
+                // an untitled outline only groups its children, which are visited in its place
+                pending_refs.push(first_child_ref);
+            }
+        }
         if let Some(title) = title_opt {
-            let bookmark_opt = if let Some(dest) = current_outline.dest.as_ref() {
-                let dest_string = dest
-                    .as_string().expect("destination not a string")
-                    .to_string().expect("failed to decode string");
-                Some(Bookmark {
+            if let Some(destination) = outline_item_destination(&current_outline, &title, quiet) {
+                ret.push(Bookmark {
                     index: ret.len(),
                     title,
-                    destination: BookmarkDestination::Named(dest_string),
-                })
-            } else if let Some(action) = current_outline.action.as_ref() {
-                match action {
-                    Action::Goto(goto) => Some(goto),
-                    other => {
-                        println!("alternative action for {:?}: {:?}", title, other);
-                        None
-                    },
-                }
-                    .and_then(|mnd| match mnd {
-                        MaybeNamedDest::Direct(dest) => {
-                            dest.page.map(|page| Bookmark {
-                                index: ret.len(),
-                                title,
-                                destination: BookmarkDestination::Page(page),
-                            })
-                        },
-                        MaybeNamedDest::Named(nd) => {
-                            let destination_text = nd.to_string()
-                                .expect("failed to decode destination string");
-                            Some(Bookmark {
-                                index: ret.len(),
-                                title,
-                                destination: BookmarkDestination::Named(destination_text),
-                            })
-                        },
-                    })
-            } else {
-                None
-            };
-            if let Some(bookmark) = bookmark_opt {
-                ret.push(bookmark);
+                    destination,
+                });
             }
         }
+    }
+    ret
+}
 
-        let next_outline_ref = match current_outline.next {
-            Some(n) => n,
-            None => break,
-        };
-        current_outline = resolve.get(next_outline_ref)
-            .expect("failed to resolve next outline");
+
+/// Returns all bookmarks at every nesting level, in document order (each bookmark followed by its
+/// children).
+///
+/// Untitled outline items are skipped but their children are included.
+pub(crate) fn get_all_bookmarks<R: Resolve>(pdf_root: &Catalog, resolve: &R, quiet: bool) -> Vec<Bookmark> {
+    let Some(outlines) = pdf_root.outlines.as_ref() else { return Vec::with_capacity(0) };
+    let Some(first_outline_ref) = outlines.first else { return Vec::with_capacity(0) };
+    let mut ret = Vec::new();
+    // the outlines still to be visited, the next one on top
+    let mut pending_refs = vec![first_outline_ref];
+    while let Some(outline_ref) = pending_refs.pop() {
+        let current_outline = resolve.get(outline_ref)
+            .expect("failed to resolve outline");
+        if let Some(next_outline_ref) = current_outline.next {
+            pending_refs.push(next_outline_ref);
+        }
+        if let Some(first_child_ref) = current_outline.first {
+            // children come before the next sibling
+            pending_refs.push(first_child_ref);
+        }
+
+        let Some(title) = current_outline.title.as_ref().map(decode_text_string) else { continue };
+        if let Some(destination) = outline_item_destination(&current_outline, &title, quiet) {
+            ret.push(Bookmark {
+                index: ret.len(),
+                title,
+                destination,
+            });
+        }
     }
     ret
 }
 
 
+/// Obtains the destination of an outline item, either given directly or via its action.
+fn outline_item_destination(outline_item: &OutlineItem, title: &str, quiet: bool) -> Option<BookmarkDestination> {
+    if let Some(dest) = outline_item.dest.as_ref() {
+        let dest_string = destination_name_key(
+            dest.as_string().expect("destination not a string")
+        );
+        Some(BookmarkDestination::Named(dest_string))
+    } else if let Some(Action::Other(action_dict)) = outline_item.action.as_ref() {
+        let destination_opt = other_action_destination(action_dict);
+        if destination_opt.is_none() && !quiet {
+            eprintln!("alternative action for {:?}: {:?}", title, action_dict);
+        }
+        destination_opt
+    } else if let Some(Action::Goto(goto)) = outline_item.action.as_ref() {
+        match goto {
+            MaybeNamedDest::Direct(dest) => dest.page.map(BookmarkDestination::Page),
+            MaybeNamedDest::Named(nd) => Some(BookmarkDestination::Named(destination_name_key(nd))),
+        }
+    } else {
+        None
+    }
+}
+
+
+/// Obtains the destination of a non-GoTo outline action, if it navigates to a fixed page.
+///
+/// Supports the named actions FirstPage and LastPage as well as JavaScript actions consisting of
+/// an assignment to `this.pageNum`. Relative navigation (NextPage, PrevPage) has no fixed
+/// destination and is not supported.
+fn other_action_destination(action_dict: &Dictionary) -> Option<BookmarkDestination> {
+    let action_type = action_dict.get("S")?.as_name().ok()?;
+    match action_type {
+        "Named" => {
+            match action_dict.get("N")?.as_name().ok()? {
+                "FirstPage" => Some(BookmarkDestination::PageIndex(0)),
+                "LastPage" => Some(BookmarkDestination::LastPage),
+                _ => None,
+            }
+        },
+        "JavaScript" => {
+            // e.g. "this.pageNum = 4;" (pageNum is 0-based)
+            let script = action_dict.get("JS")?.as_string().ok()?.to_string_lossy();
+            let statement = script.trim().trim_end_matches(';');
+            let value = statement.strip_prefix("this.pageNum")?
+                .trim_start()
+                .strip_prefix('=')?
+                .trim();
+            value.parse().ok().map(BookmarkDestination::PageIndex)
+        },
+        _ => None,
+    }
+}
+
+
 pub(crate) fn collect_page_references<R: Resolve>(page_tree: &PageTree, resolve: &R, page_refs: &mut Vec<Ref<Page>>, depth: usize) {
     if depth == 0 {
         panic!("in too deep");
@@ -228,6 +506,243 @@ pub(crate) fn get_page_references<R: Resolve>(pdf_root: &Catalog, resolve: &R) -
 }
 
 
+/// A range of pages numbered in the same style, such as "A-1", "A-2", ...
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct PageLabelRange {
+    /// The numbering style: 'D' (decimal), 'R'/'r' (upper/lowercase roman numerals), 'A'/'a'
+    /// (upper/lowercase letters) or `None` for labels consisting only of the prefix.
+    pub style: Option<char>,
+    pub prefix: String,
+    /// The number of the first page of the range.
+    pub start: u32,
+}
+
+
+/// Reads the page labels from the `/PageLabels` number tree of the document catalog, keyed by the
+/// index of the first page of each range. Malformed entries are skipped; if the document has no
+/// page labels, the map is empty.
+pub(crate) fn get_page_labels<R: Resolve>(root_ref: PlainRef, resolve: &R) -> BTreeMap<u32, PageLabelRange> {
+    let mut labels = BTreeMap::new();
+    let Ok(root) = resolve.resolve(root_ref).and_then(|r| r.into_dictionary()) else { return labels };
+    let Some(page_labels) = root.get("PageLabels") else { return labels };
+    let Ok(page_labels) = page_labels.clone().resolve(resolve).and_then(|p| p.into_dictionary()) else { return labels };
+    collect_page_labels(&page_labels, resolve, &mut labels, 16);
+    labels
+}
+
+fn collect_page_labels<R: Resolve>(node: &Dictionary, resolve: &R, labels: &mut BTreeMap<u32, PageLabelRange>, depth: usize) {
+    if depth == 0 {
+        return;
+    }
+
+    if let Some(Ok(nums)) = node.get("Nums").map(|n| n.clone().resolve(resolve).and_then(|n| n.into_array())) {
+        for pair in nums.chunks_exact(2) {
+            let Ok(first_page) = pair[0].as_u32() else { continue };
+            let Ok(label) = pair[1].clone().resolve(resolve).and_then(|l| l.into_dictionary()) else { continue };
+            let style = label.get("S")
+                .and_then(|s| s.as_name().ok())
+                .and_then(|s| s.chars().next());
+            let prefix = label.get("P")
+                .and_then(|p| p.as_string().ok())
+                .map(decode_text_string)
+                .unwrap_or_default();
+            let start = label.get("St")
+                .and_then(|st| st.as_u32().ok())
+                .unwrap_or(1);
+            labels.insert(first_page, PageLabelRange { style, prefix, start });
+        }
+    }
+    if let Some(Ok(kids)) = node.get("Kids").map(|k| k.clone().resolve(resolve).and_then(|k| k.into_array())) {
+        for kid in kids {
+            if let Ok(kid_node) = kid.resolve(resolve).and_then(|k| k.into_dictionary()) {
+                collect_page_labels(&kid_node, resolve, labels, depth - 1);
+            }
+        }
+    }
+}
+
+/// Returns the label of the page with the given index, or `None` if the document has no page
+/// labels or none apply to this page.
+pub(crate) fn page_label(labels: &BTreeMap<u32, PageLabelRange>, page_index: u32) -> Option<String> {
+    let (first_page, range) = labels.range(..=page_index).next_back()?;
+    let number = range.start + (page_index - first_page);
+    let formatted = match range.style {
+        Some('D') => number.to_string(),
+        Some('R') => to_roman(number),
+        Some('r') => to_roman(number).to_lowercase(),
+        Some('A') => to_letters(number),
+        Some('a') => to_letters(number).to_lowercase(),
+        _ => String::new(),
+    };
+    Some(format!("{}{}", range.prefix, formatted))
+}
+
+fn to_roman(mut number: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+        (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+    ];
+    let mut ret = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            ret.push_str(numeral);
+            number -= value;
+        }
+    }
+    ret
+}
+
+/// Letter numbering as defined by PDF: A to Z, then AA to ZZ, then AAA to ZZZ etc.
+fn to_letters(number: u32) -> String {
+    if number == 0 {
+        return String::new();
+    }
+    let letter = char::from(b'A' + ((number - 1) % 26) as u8);
+    let count = usize::try_from((number - 1) / 26 + 1).unwrap();
+    std::iter::repeat_n(letter, count).collect()
+}
+
+
+/// Collects the marked content of the given page from the structure tree of a tagged document,
+/// grouped into lines in the order of the tree: the marked content of each table row (TR) forms a
+/// line, as does the marked content directly contained in any other element. Returns `None` if the
+/// document has no structure tree.
+pub(crate) fn get_struct_tree_lines<R: Resolve>(root_ref: PlainRef, page_ref: PlainRef, resolve: &R) -> Option<Vec<Vec<i32>>> {
+    let root = resolve.resolve(root_ref).and_then(|r| r.into_dictionary()).ok()?;
+    let struct_tree_root = root.get("StructTreeRoot")?
+        .clone()
+        .resolve(resolve)
+        .and_then(|s| s.into_dictionary())
+        .ok()?;
+    let mut lines = Vec::new();
+    walk_struct_element(&struct_tree_root, None, page_ref, resolve, &mut lines, None, 64);
+    Some(lines)
+}
+
+fn walk_struct_element<R: Resolve>(
+    element: &Dictionary,
+    inherited_page: Option<PlainRef>,
+    page_ref: PlainRef,
+    resolve: &R,
+    lines: &mut Vec<Vec<i32>>,
+    mut row: Option<&mut Vec<i32>>,
+    depth: usize,
+) {
+    if depth == 0 {
+        return;
+    }
+
+    let element_page = element.get("Pg")
+        .and_then(|pg| pg.clone().into_reference().ok())
+        .or(inherited_page);
+    let starts_row = row.is_none()
+        && element.get("S").and_then(|s| s.as_name().ok()) == Some("TR");
+    let mut own_row = Vec::new();
+    let mut own = Vec::new();
+
+    let kids = match element.get("K").map(|k| k.clone().resolve(resolve)) {
+        Some(Ok(Primitive::Array(kids))) => kids,
+        Some(Ok(kid)) => vec![kid],
+        _ => Vec::with_capacity(0),
+    };
+    for kid in kids {
+        let Ok(kid) = kid.resolve(resolve) else { continue };
+        let mcid_opt = match &kid {
+            Primitive::Integer(mcid) if element_page == Some(page_ref) => Some(*mcid),
+            Primitive::Dictionary(d) if d.get("Type").and_then(|t| t.as_name().ok()) == Some("MCR") => {
+                // marked-content reference, possibly on another page
+                let mcr_page = d.get("Pg")
+                    .and_then(|pg| pg.clone().into_reference().ok())
+                    .or(element_page);
+                d.get("MCID")
+                    .and_then(|m| m.as_integer().ok())
+                    .filter(|_| mcr_page == Some(page_ref))
+            },
+            _ => None,
+        };
+        if let Some(mcid) = mcid_opt {
+            if let Some(r) = row.as_deref_mut() {
+                r.push(mcid);
+            } else if starts_row {
+                own_row.push(mcid);
+            } else {
+                own.push(mcid);
+            }
+            continue;
+        }
+
+        let Primitive::Dictionary(kid_element) = kid else { continue };
+        if kid_element.get("S").is_none() {
+            // e.g. an object reference
+            continue;
+        }
+        if let Some(r) = row.as_deref_mut() {
+            walk_struct_element(&kid_element, element_page, page_ref, resolve, lines, Some(r), depth - 1);
+        } else if starts_row {
+            walk_struct_element(&kid_element, element_page, page_ref, resolve, lines, Some(&mut own_row), depth - 1);
+        } else {
+            // keep the lines in the order of the tree
+            if !own.is_empty() {
+                lines.push(std::mem::take(&mut own));
+            }
+            walk_struct_element(&kid_element, element_page, page_ref, resolve, lines, None, depth - 1);
+        }
+    }
+    if !own_row.is_empty() {
+        lines.push(own_row);
+    }
+    if !own.is_empty() {
+        lines.push(own);
+    }
+}
+
+
+/// Collects the fonts available to a page, including those defined in the resources of its
+/// ancestors in the page tree. Definitions closer to the page take precedence.
+pub(crate) fn get_page_fonts(page: &Page) -> HashMap<&str, &MaybeRef<Font>> {
+    const MAX_ANCESTORS: usize = 16;
+
+    let mut ancestors: Vec<&PageTree> = Vec::new();
+    let mut current_tree: &PageTree = &page.parent;
+    loop {
+        ancestors.push(current_tree);
+        let Some(parent) = current_tree.parent.as_ref() else { break };
+        if ancestors.len() >= MAX_ANCESTORS {
+            // most likely a cycle; the fonts of the nearest ancestors are hopefully enough
+            eprintln!("page tree is deeper than {} levels; ignoring the resources further up", MAX_ANCESTORS);
+            break;
+        }
+        current_tree = parent;
+    }
+
+    let mut fonts = HashMap::new();
+    for tree in ancestors.iter().rev() {
+        if let Some(resources) = tree.resources.as_ref() {
+            fonts.extend(resources.fonts());
+        }
+    }
+    if let Some(resources) = page.resources.as_ref() {
+        fonts.extend(resources.fonts());
+    }
+    fonts
+}
+
+
+/// Converts the name of a destination into the key used to look it up.
+///
+/// Destination names are byte strings that need not be valid in any encoding (octal escapes can
+/// produce arbitrary bytes, including NULs). Bytes that are not valid UTF-8 are mapped one-to-one
+/// to the Latin-1 code points so that distinct names never collide. Outline entries and the name
+/// tree must both be converted using this function for the lookup to work.
+pub(crate) fn destination_name_key(name: &PdfString) -> String {
+    let bytes = name.as_bytes();
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_owned(),
+        Err(_) => bytes.iter().map(|&b| char::from(b)).collect(),
+    }
+}
+
+
 pub(crate) fn get_destination_pages<R: Resolve>(pdf_root: &Catalog, resolve: &R) -> HashMap<String, u32> {
     let page_refs = get_page_references(pdf_root, resolve);
     let Some(names_ref) = pdf_root.names.as_ref() else { return HashMap::with_capacity(0) };
@@ -235,7 +750,7 @@ pub(crate) fn get_destination_pages<R: Resolve>(pdf_root: &Catalog, resolve: &R)
     let Some(dests) = names.dests.as_ref() else { return HashMap::with_capacity(0) };
     let mut ret = HashMap::new();
     dests.walk(resolve, &mut |name, dest_opt| {
-        let Ok(dest_name) = name.to_string() else { return };
+        let dest_name = destination_name_key(name);
         let Some(dest) = dest_opt else { return };
         let Some(page_ref) = dest.page else { return };
         let page_index = page_refs.iter().position(|pr| pr == &page_ref);
@@ -257,31 +772,108 @@ pub(crate) fn bookmark_destination_to_page_index(
 ) -> Option<u32> {
     match destination {
         BookmarkDestination::Named(name) => {
-            destination_pages.get(name).map(|v| *v)
+            if let Some(page_index) = destination_pages.get(name) {
+                return Some(*page_index);
+            }
+
+            // some documents use the (1-based) page number as the destination name
+            let page_number: u32 = name.trim().parse().ok()?;
+            let page_index = page_number.checked_sub(1)?;
+            if usize::try_from(page_index).ok()? < page_references.len() {
+                Some(page_index)
+            } else {
+                None
+            }
         },
         BookmarkDestination::Page(page_ref) => {
             page_references.iter()
                 .position(|pr| pr == page_ref)
                 .map(|i| u32::try_from(i).unwrap())
         },
+        BookmarkDestination::PageIndex(page_index) => {
+            if usize::try_from(*page_index).ok()? < page_references.len() {
+                Some(*page_index)
+            } else {
+                None
+            }
+        },
+        BookmarkDestination::LastPage => {
+            page_references.len()
+                .checked_sub(1)
+                .map(|i| u32::try_from(i).unwrap())
+        },
+    }
+}
+
+
+/// Whether the font uses vertical writing mode (WMode 1), in which glyphs advance downwards.
+///
+/// Only detected for fonts using a predefined CMap such as Identity-V; the `pdf` crate does not
+/// expose the WMode of embedded CMaps.
+pub(crate) fn is_vertical_font(font: &Font) -> bool {
+    match font.encoding() {
+        // predefined vertical CMaps are named "...-V"
+        Some(encoding) => matches!(&encoding.base, BaseEncoding::Other(name) if name.ends_with("-V")),
+        None => false,
     }
 }
 
 
-pub(crate) fn font_decode<R: Resolve>(current_font_opt: Option<&MaybeRef<Font>>, text: PdfString, resolve: &R) -> Option<String> {
-    let Some(current_font) = current_font_opt else { return None };
+/// Describes how text in the given font is encoded, for debugging decoding failures.
+pub(crate) fn describe_font_encoding<R: Resolve>(font: &MaybeRef<Font>, resolve: &R) -> String {
+    let base_font = font.name.as_ref()
+        .map(|n| n.as_str())
+        .unwrap_or("(unnamed)");
+    let to_unicode = match font.to_unicode(resolve) {
+        Some(Ok(_)) => "present",
+        Some(Err(_)) => "broken",
+        None => "absent",
+    };
+    let encoding = match font.encoding() {
+        Some(encoding) => format!("{:?} with {} differences", encoding.base, encoding.differences.len()),
+        None => "none".to_owned(),
+    };
+    format!("{} ({:?}), ToUnicode {}, encoding {}", base_font, font.subtype, to_unicode, encoding)
+}
+
+
+/// Text decoded using a font.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct DecodedText {
+    pub text: String,
+    pub path: DecodePath,
+    /// The character codes that could not be decoded and have been dropped from the text.
+    pub undecoded: Vec<u16>,
+}
+
+
+pub(crate) fn font_decode<R: Resolve>(current_font_opt: Option<&MaybeRef<Font>>, text: PdfString, resolve: &R, quiet: bool) -> Option<DecodedText> {
+    let current_font = current_font_opt?;
     let text_bytes = text.as_bytes();
-    if let Some(itu) = current_font.to_unicode(resolve) {
+    // a broken ToUnicode map falls through to the encoding
+    let to_unicode_opt = current_font.to_unicode(resolve)
+        .and_then(|itu| itu.ok());
+    if let Some(index_to_unicode) = to_unicode_opt {
         // direct to-Unicode map
-        let index_to_unicode = itu.expect("failed to obtain Unicode map");
         let mut ret = String::with_capacity(text_bytes.len() / 2);
-        for i in (0..text_bytes.len()).step_by(2) {
-            let index = u16::from_be_bytes(text_bytes[i..i+2].try_into().unwrap());
-            let unicode = index_to_unicode.get(index)
-                .expect("unexpected index");
-            ret.push_str(unicode);
+        let mut undecoded = Vec::new();
+        let mut codes = text_bytes.chunks_exact(2);
+        for code in &mut codes {
+            let index = u16::from_be_bytes([code[0], code[1]]);
+            match index_to_unicode.get(index) {
+                Some(unicode) => ret.push_str(unicode),
+                None => undecoded.push(index),
+            }
+        }
+        if let [truncated] = codes.remainder() {
+            // a two-byte code cut short; keep what was decoded up to it and report the byte as
+            // undecoded along with the rest (in detail with --report-undecoded)
+            if !quiet {
+                eprintln!("string of odd length {} ends in a truncated two-byte code", text_bytes.len());
+            }
+            undecoded.push((*truncated).into());
         }
-        Some(ret)
+        Some(DecodedText { text: ret, path: DecodePath::ToUnicode, undecoded })
     } else if let Some(encoding) = current_font.encoding() {
         // use encoding
         let mut encoding_map = match encoding.base {
@@ -304,12 +896,14 @@ pub(crate) fn font_decode<R: Resolve>(current_font_opt: Option<&MaybeRef<Font>>,
 
         // decode
         let mut ret = String::with_capacity(text_bytes.len());
+        let mut undecoded = Vec::new();
         for b in text_bytes {
-            if let Some(c) = encoding_map.get(b) {
-                ret.push(*c);
+            match encoding_map.get(b) {
+                Some(c) => ret.push(*c),
+                None => undecoded.push((*b).into()),
             }
         }
-        Some(ret)
+        Some(DecodedText { text: ret, path: DecodePath::BaseEncoding, undecoded })
     } else {
         None
     }
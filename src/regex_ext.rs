@@ -15,12 +15,12 @@ impl PartialEq for SerializableRegex {
 impl Eq for SerializableRegex {}
 impl PartialOrd for SerializableRegex {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.as_str().partial_cmp(other.0.as_str())
+        Some(self.cmp(other))
     }
 }
 impl Ord for SerializableRegex {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        self.0.as_str().cmp(other.0.as_str())
     }
 }
 impl Hash for SerializableRegex {
@@ -37,7 +37,7 @@ impl<'de> Deserialize<'de> for SerializableRegex {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let s = String::deserialize(deserializer)?;
         let regex = Regex::new(&s)
-            .map_err(|e| D::Error::custom(e))?;
+            .map_err(D::Error::custom)?;
         Ok(Self(regex))
     }
 }
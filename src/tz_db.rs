@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use chrono::{Datelike, DateTime, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz, TZ_VARIANTS};
+use once_cell::sync::Lazy;
+
+use crate::utc_offset::UtcOffset;
+
+
+/// The current offsets of a time zone from the time zone database, in seconds.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct ZoneOffsets {
+    pub zone: Tz,
+    pub standard: i64,
+    pub daylight: Option<i64>,
+}
+
+
+static ZONE_OFFSETS: Lazy<Vec<ZoneOffsets>> = Lazy::new(|| {
+    // sample the middle of winter and summer of both hemispheres this year
+    let year = Utc::now().year();
+    let samples = [
+        NaiveDate::from_ymd_opt(year, 1, 15).unwrap().and_hms_opt(12, 0, 0).unwrap(),
+        NaiveDate::from_ymd_opt(year, 7, 15).unwrap().and_hms_opt(12, 0, 0).unwrap(),
+    ];
+
+    let mut zone_offsets = Vec::with_capacity(TZ_VARIANTS.len());
+    for zone in TZ_VARIANTS {
+        let mut standard = None;
+        let mut daylight = None;
+        for sample in &samples {
+            let offset = zone.offset_from_utc_datetime(sample);
+            let base = offset.base_utc_offset().num_seconds();
+            let dst = offset.dst_offset().num_seconds();
+            standard = Some(base);
+            if dst != 0 {
+                daylight = Some(base + dst);
+            }
+        }
+        zone_offsets.push(ZoneOffsets {
+            zone,
+            standard: standard.unwrap(),
+            daylight,
+        });
+    }
+    zone_offsets
+});
+
+
+/// The zone.tab file of the time zone database, which chrono-tz does not include. It is copied
+/// from the tzdata release bundled by chrono-tz, which the first line ("# version 2025b") records;
+/// when chrono-tz is upgraded to a newer release, the copy must be replaced by that release's
+/// zone.tab (and the version line added again), which the assertion below enforces.
+const ZONE_TAB: &str = include_str!("zone.tab");
+
+const _: () = assert!(
+    zone_tab_has_version(ZONE_TAB.as_bytes(), chrono_tz::IANA_TZDB_VERSION.as_bytes()),
+    "src/zone.tab is not from the tzdata release bundled by chrono-tz; replace it with the zone.tab of that release",
+);
+
+/// Returns whether the first line of the zone.tab file is "# version " followed by the given
+/// version. (Comparing strings is not possible in constant evaluation.)
+const fn zone_tab_has_version(zone_tab: &[u8], version: &[u8]) -> bool {
+    const PREFIX: &[u8] = b"# version ";
+    if zone_tab.len() <= PREFIX.len() + version.len() {
+        return false;
+    }
+    let mut index = 0;
+    while index < PREFIX.len() {
+        if zone_tab[index] != PREFIX[index] {
+            return false;
+        }
+        index += 1;
+    }
+    let mut index = 0;
+    while index < version.len() {
+        if zone_tab[PREFIX.len() + index] != version[index] {
+            return false;
+        }
+        index += 1;
+    }
+    zone_tab[PREFIX.len() + version.len()] == b'\n'
+}
+
+/// The time zones of each country (by ISO 3166 alpha-2 code), from zone.tab.
+static COUNTRY_ZONES: Lazy<HashMap<&'static str, Vec<&'static str>>> = Lazy::new(|| {
+    let mut country_zones: HashMap<&'static str, Vec<&'static str>> = HashMap::new();
+    for line in ZONE_TAB.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        // country code, coordinates, zone name and optional comments, separated by tabs
+        let mut fields = line.split('\t');
+        let (Some(country), Some(_coordinates), Some(zone)) = (fields.next(), fields.next(), fields.next()) else {
+            panic!("invalid zone.tab line {:?}", line);
+        };
+        country_zones.entry(country).or_default().push(zone);
+    }
+    country_zones
+});
+
+
+/// Returns the names of the time zones of the country with the given ISO 3166 alpha-2 code.
+pub(crate) fn country_zones(country: &str) -> Option<&'static [&'static str]> {
+    COUNTRY_ZONES.get(country.to_uppercase().as_str())
+        .map(|zones| zones.as_slice())
+}
+
+
+/// Returns the prefixes of the names of the time zones plausible for the ICAO region (encoded by
+/// the first letter of the ICAO code) of the given airport.
+pub(crate) fn region_prefixes(icao: &str) -> Option<&'static [&'static str]> {
+    let prefixes: &'static [&'static str] = match icao.chars().next()? {
+        'A'|'N' => &["Pacific/"],
+        'B' => &["America/", "Atlantic/", "Europe/"],
+        'C'|'K'|'M' => &["America/"],
+        'D'|'H' => &["Africa/"],
+        'E' => &["Europe/", "Atlantic/", "Arctic/"],
+        'F' => &["Africa/", "Indian/", "Atlantic/"],
+        'G' => &["Africa/", "Atlantic/"],
+        'L' => &["Europe/", "Asia/", "Atlantic/"],
+        'O'|'R'|'W'|'Z' => &["Asia/"],
+        'P' => &["America/", "Pacific/"],
+        'S'|'T' => &["America/", "Atlantic/"],
+        'U' => &["Europe/", "Asia/"],
+        'V' => &["Asia/", "Indian/"],
+        'Y' => &["Australia/", "Antarctica/"],
+        _ => return None,
+    };
+    Some(prefixes)
+}
+
+
+/// Returns the names of all zones in the time zone database whose current standard and daylight
+/// offsets match the given ones.
+///
+/// If the country of the airport is passed, only the zones of that country are returned. Otherwise,
+/// if the ICAO code of an airport is passed, only zones plausible for its ICAO region are returned.
+pub(crate) fn candidate_zones(
+    utc_standard: UtcOffset,
+    utc_daylight: Option<UtcOffset>,
+    region_hint_icao: Option<&str>,
+    country_hint: Option<&str>,
+) -> Vec<String> {
+    let standard_seconds = i64::from(utc_standard.minutes()) * 60;
+    let daylight_seconds = utc_daylight.map(|d| i64::from(d.minutes()) * 60);
+    let country_zones_opt = country_hint.and_then(country_zones);
+    let prefixes_opt = region_hint_icao.and_then(region_prefixes);
+    ZONE_OFFSETS.iter()
+        .filter(|zo| zo.standard == standard_seconds && zo.daylight == daylight_seconds)
+        .map(|zo| zo.zone.name())
+        .filter(|name| match (country_zones_opt, prefixes_opt) {
+            (Some(zones), _) => zones.contains(name),
+            (None, Some(prefixes)) => prefixes.iter().any(|p| name.starts_with(p)),
+            (None, None) => true,
+        })
+        .map(|name| name.to_owned())
+        .collect()
+}
+
+
+/// A change of offset of a time zone.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Transition {
+    pub instant: i64,
+    /// The offset in effect before the transition, in seconds.
+    pub offset_before: i64,
+    /// Whether daylight saving time is in effect after the transition.
+    pub daylight_after: bool,
+}
+
+
+/// The total offset from UTC in seconds and whether it includes daylight saving time.
+fn total_offset(zone: Tz, instant: i64) -> (i64, bool) {
+    let offset = zone.offset_from_utc_datetime(&DateTime::from_timestamp(instant, 0).unwrap().naive_utc());
+    let dst = offset.dst_offset().num_seconds();
+    (offset.base_utc_offset().num_seconds() + dst, dst != 0)
+}
+
+
+/// Finds the offset changes of the zone within the given year (UTC).
+fn year_transitions(zone: Tz, year: i32) -> Vec<Transition> {
+    const DAY: i64 = 24 * 60 * 60;
+    let year_start = NaiveDate::from_ymd_opt(year, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+    let year_end = NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+
+    let mut transitions = Vec::new();
+    let mut day_start = year_start;
+    while day_start < year_end {
+        let day_end = (day_start + DAY).min(year_end);
+        let (offset_before, _) = total_offset(zone, day_start);
+        let (offset_after, daylight_after) = total_offset(zone, day_end);
+        if offset_before != offset_after {
+            // find the first second with the new offset
+            let (mut low, mut high) = (day_start, day_end);
+            while high - low > 1 {
+                let middle = low + (high - low) / 2;
+                if total_offset(zone, middle).0 == offset_before {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            transitions.push(Transition { instant: high, offset_before, daylight_after });
+        }
+        day_start = day_end;
+    }
+    transitions
+}
+
+
+/// Formats a transition as a POSIX TZ rule: "Mm.w.d" (month, week with 5 meaning the last one,
+/// weekday with 0 meaning Sunday) followed by the local time of the transition unless it is the
+/// default of 02:00.
+fn posix_rule(transition: &Transition) -> String {
+    let local = DateTime::from_timestamp(transition.instant + transition.offset_before, 0)
+        .unwrap()
+        .naive_utc();
+    let date = local.date();
+    let is_last_week = (date + Duration::days(7)).month() != date.month();
+    let week = if is_last_week { 5 } else { (date.day() - 1) / 7 + 1 };
+    let mut rule = format!("M{}.{}.{}", date.month(), week, date.weekday().num_days_from_sunday());
+    let seconds = i64::from(local.time().num_seconds_from_midnight());
+    if seconds != 2 * 60 * 60 {
+        rule.push('/');
+        rule.push_str(&posix_time(seconds));
+    }
+    rule
+}
+
+
+/// Formats a number of seconds as a POSIX TZ time ("h", "h:mm" or "h:mm:ss").
+fn posix_time(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let (hours, minutes, secs) = (seconds.abs() / 3600, seconds.abs() / 60 % 60, seconds.abs() % 60);
+    if secs != 0 {
+        format!("{}{}:{:02}:{:02}", sign, hours, minutes, secs)
+    } else if minutes != 0 {
+        format!("{}{}:{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{}", sign, hours)
+    }
+}
+
+
+/// Formats a time zone abbreviation for a POSIX TZ string, deriving a numeric one (e.g. "<+03>")
+/// if the zone has none or it contains other characters than letters.
+fn posix_abbreviation(abbreviation: Option<&str>, offset_seconds: i64) -> String {
+    match abbreviation {
+        Some(abbr) if abbr.len() >= 3 && abbr.chars().all(|c| c.is_ascii_alphabetic()) => abbr.to_owned(),
+        Some(abbr) => format!("<{}>", abbr),
+        None => {
+            let sign = if offset_seconds < 0 { '-' } else { '+' };
+            let (hours, minutes) = (offset_seconds.abs() / 3600, offset_seconds.abs() / 60 % 60);
+            if minutes == 0 {
+                format!("<{}{:02}>", sign, hours)
+            } else {
+                format!("<{}{:02}{:02}>", sign, hours, minutes)
+            }
+        },
+    }
+}
+
+
+/// Derives the POSIX TZ string (e.g. "CET-1CEST,M3.5.0,M10.5.0/3") of the zone with the given name
+/// from its transitions this year and the next.
+///
+/// Returns `None` if the zone is unknown or its transitions cannot be expressed as a POSIX rule,
+/// e.g. because they differ between the two years or do not alternate between standard and
+/// daylight saving time once each.
+pub(crate) fn posix_tz_string(iana: &str) -> Option<String> {
+    let zone: Tz = iana.parse().ok()?;
+    let year = Utc::now().year();
+    let this_year = year_transitions(zone, year);
+    let next_year = year_transitions(zone, year + 1);
+
+    let reference_instant = DateTime::from_timestamp(
+        NaiveDate::from_ymd_opt(year, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp(),
+        0,
+    ).unwrap();
+    if this_year.is_empty() && next_year.is_empty() {
+        let offset = zone.offset_from_utc_datetime(&reference_instant.naive_utc());
+        let seconds = offset.base_utc_offset().num_seconds() + offset.dst_offset().num_seconds();
+        // POSIX offsets are positive west of Greenwich
+        return Some(format!("{}{}", posix_abbreviation(offset.abbreviation(), seconds), posix_time(-seconds)));
+    }
+
+    // exactly one transition into and one out of daylight saving time
+    let rules_of = |transitions: &[Transition]| -> Option<(String, String)> {
+        let [first, second] = transitions else { return None };
+        let (start, end) = match (first.daylight_after, second.daylight_after) {
+            (true, false) => (first, second),
+            (false, true) => (second, first),
+            _ => return None,
+        };
+        Some((posix_rule(start), posix_rule(end)))
+    };
+    let rules = rules_of(&this_year)?;
+    if rules_of(&next_year)? != rules {
+        return None;
+    }
+
+    let start = this_year.iter().find(|t| t.daylight_after).unwrap();
+    let end = this_year.iter().find(|t| !t.daylight_after).unwrap();
+    let standard_offset = zone.offset_from_utc_datetime(
+        &DateTime::from_timestamp(end.instant, 0).unwrap().naive_utc()
+    );
+    let daylight_offset = zone.offset_from_utc_datetime(
+        &DateTime::from_timestamp(start.instant, 0).unwrap().naive_utc()
+    );
+    let standard_seconds = standard_offset.base_utc_offset().num_seconds() + standard_offset.dst_offset().num_seconds();
+    let daylight_seconds = daylight_offset.base_utc_offset().num_seconds() + daylight_offset.dst_offset().num_seconds();
+
+    let mut posix = format!(
+        "{}{}{}",
+        posix_abbreviation(standard_offset.abbreviation(), standard_seconds),
+        posix_time(-standard_seconds),
+        posix_abbreviation(daylight_offset.abbreviation(), daylight_seconds),
+    );
+    if daylight_seconds - standard_seconds != 60 * 60 {
+        // the default is one hour ahead of standard time
+        posix.push_str(&posix_time(-daylight_seconds));
+    }
+    write!(posix, ",{},{}", rules.0, rules.1).unwrap();
+    Some(posix)
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{Error, Visitor};
+
+
+/// An offset from UTC with a precision of minutes.
+///
+/// Serialized as an integer number of hours if the offset is a whole number of hours (as in
+/// `utc_standard = -5`) and as a string such as `"+5:30"` otherwise.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct UtcOffset(i16);
+impl UtcOffset {
+    pub const fn from_minutes(minutes: i16) -> Self { Self(minutes) }
+    pub const fn minutes(&self) -> i16 { self.0 }
+
+    pub fn whole_hours(&self) -> Option<i16> {
+        if self.0 % 60 == 0 {
+            Some(self.0 / 60)
+        } else {
+            None
+        }
+    }
+
+    pub fn checked_add(&self, other: UtcOffset) -> Option<UtcOffset> {
+        self.0.checked_add(other.0).map(Self)
+    }
+}
+impl fmt::Display for UtcOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        let hours = self.0.unsigned_abs() / 60;
+        let minutes = self.0.unsigned_abs() % 60;
+        if minutes == 0 {
+            write!(f, "{}{}", sign, hours)
+        } else {
+            write!(f, "{}{}:{:02}", sign, hours, minutes)
+        }
+    }
+}
+impl FromStr for UtcOffset {
+    type Err = String;
+
+    /// Parses offsets such as "-5", "+1", "+5:30", "+0530", "-930" and "+5.5".
+    ///
+    /// Without a colon or decimal point, one or two digits are hours and three or four digits are
+    /// hours followed by two digits of minutes. Fractional hours must amount to whole minutes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, unsigned) = if let Some(rest) = s.strip_prefix('-') {
+            (true, rest)
+        } else if let Some(rest) = s.strip_prefix('+') {
+            (false, rest)
+        } else {
+            (false, s)
+        };
+
+        let (hours_str, minutes_str) = if let Some((h, m)) = unsigned.split_once(':') {
+            if m.len() != 2 {
+                return Err(format!("invalid minutes in UTC offset {:?}", s));
+            }
+            (h, m.to_owned())
+        } else if let Some((h, fraction)) = unsigned.split_once('.') {
+            let fraction_value: u32 = match fraction.len() {
+                1|2 if fraction.chars().all(|c| c.is_ascii_digit()) => fraction.parse().unwrap(),
+                _ => return Err(format!("invalid fractional hours in UTC offset {:?}", s)),
+            };
+            let denominator = 10u32.pow(fraction.len() as u32);
+            if !(fraction_value * 60).is_multiple_of(denominator) {
+                return Err(format!("fractional hours in UTC offset {:?} are not whole minutes", s));
+            }
+            (h, (fraction_value * 60 / denominator).to_string())
+        } else {
+            match unsigned.len() {
+                1|2 => (unsigned, "0".to_owned()),
+                3|4 => {
+                    let (h, m) = unsigned.split_at(unsigned.len() - 2);
+                    (h, m.to_owned())
+                },
+                _ => return Err(format!("invalid UTC offset {:?}", s)),
+            }
+        };
+        if hours_str.is_empty() || !hours_str.chars().chain(minutes_str.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(format!("invalid UTC offset {:?}", s));
+        }
+
+        let hours: i16 = hours_str.parse()
+            .map_err(|_| format!("invalid hours in UTC offset {:?}", s))?;
+        let minutes: i16 = minutes_str.parse()
+            .map_err(|_| format!("invalid minutes in UTC offset {:?}", s))?;
+        if minutes >= 60 {
+            return Err(format!("invalid minutes in UTC offset {:?}", s));
+        }
+        let total = hours.checked_mul(60)
+            .and_then(|h| h.checked_add(minutes))
+            .ok_or_else(|| format!("UTC offset {:?} out of range", s))?;
+        Ok(Self(if negative { -total } else { total }))
+    }
+}
+impl Serialize for UtcOffset {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.whole_hours() {
+            Some(hours) => hours.serialize(serializer),
+            None => self.to_string().serialize(serializer),
+        }
+    }
+}
+impl<'de> Deserialize<'de> for UtcOffset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(UtcOffsetVisitor)
+    }
+}
+
+struct UtcOffsetVisitor;
+impl<'de> Visitor<'de> for UtcOffsetVisitor {
+    type Value = UtcOffset;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a number of hours or a string such as \"+5:30\"")
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        v.checked_mul(60)
+            .and_then(|m| i16::try_from(m).ok())
+            .map(UtcOffset)
+            .ok_or_else(|| E::custom(format!("UTC offset of {} hours out of range", v)))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        let signed = i64::try_from(v)
+            .map_err(|_| E::custom(format!("UTC offset of {} hours out of range", v)))?;
+        self.visit_i64(signed)
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+}